@@ -0,0 +1,2 @@
+-- Add up migration script here
+ALTER TABLE chess_board ADD COLUMN castling_rights TEXT NOT NULL DEFAULT 'KQkq';
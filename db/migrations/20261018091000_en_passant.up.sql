@@ -0,0 +1,2 @@
+-- Add up migration script here
+ALTER TABLE chess_board ADD COLUMN en_passant TEXT NULL;
@@ -0,0 +1,3 @@
+-- Add down migration script here
+ALTER TABLE chess_board DROP COLUMN winner;
+ALTER TABLE chess_board DROP COLUMN status;
@@ -0,0 +1,3 @@
+-- Add up migration script here
+ALTER TABLE chess_board ADD COLUMN status TEXT NOT NULL DEFAULT 'ONGOING';
+ALTER TABLE chess_board ADD COLUMN winner TEXT NULL REFERENCES piece_colors(color);
@@ -0,0 +1,3 @@
+-- Add down migration script here
+ALTER TABLE chess_board DROP COLUMN position_history;
+ALTER TABLE chess_board DROP COLUMN halfmove_clock;
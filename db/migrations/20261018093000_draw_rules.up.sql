@@ -0,0 +1,3 @@
+-- Add up migration script here
+ALTER TABLE chess_board ADD COLUMN halfmove_clock INTEGER NOT NULL DEFAULT 0;
+ALTER TABLE chess_board ADD COLUMN position_history TEXT NOT NULL DEFAULT '';
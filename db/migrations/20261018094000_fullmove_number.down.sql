@@ -0,0 +1,2 @@
+-- Add down migration script here
+ALTER TABLE chess_board DROP COLUMN fullmove_number;
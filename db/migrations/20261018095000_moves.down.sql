@@ -0,0 +1,3 @@
+-- Add down migration script here
+ALTER TABLE chess_board DROP COLUMN start_fen;
+DROP TABLE IF EXISTS moves;
@@ -0,0 +1,14 @@
+-- Add up migration script here
+CREATE TABLE IF NOT EXISTS moves(
+    board_name TEXT NOT NULL,
+    ply INTEGER NOT NULL,
+    from_square TEXT NOT NULL,
+    to_square TEXT NOT NULL,
+    promotion TEXT NULL,
+    san TEXT NOT NULL,
+    FOREIGN KEY (board_name) REFERENCES chess_board(board_name),
+    FOREIGN KEY (promotion) REFERENCES piece_names(name),
+    CONSTRAINT moves_PK PRIMARY KEY (board_name, ply)
+);
+
+ALTER TABLE chess_board ADD COLUMN start_fen TEXT NOT NULL DEFAULT 'rnbqkbnr/pppppppp/8/8/8/8/PPPPPPPP/RNBQKBNR w KQkq - 0 1';
@@ -0,0 +1,74 @@
+-- Add down migration script here
+-- the single hard-coded board comes back with the oldest game, the tables are built up
+-- like the migrations before did so their down scripts still find every column
+CREATE TABLE IF NOT EXISTS chess_board(
+    ID INTEGER PRIMARY KEY AUTOINCREMENT NOT NULL,
+    player_turn TEXT NOT NULL,
+    board_name TEXT NOT NULL,
+    FOREIGN KEY (player_turn) REFERENCES piece_colors(color),
+    CONSTRAINT unique_board_name UNIQUE (board_name)
+);
+ALTER TABLE chess_board ADD COLUMN castling_rights TEXT NOT NULL DEFAULT 'KQkq';
+ALTER TABLE chess_board ADD COLUMN en_passant TEXT NULL;
+ALTER TABLE chess_board ADD COLUMN status TEXT NOT NULL DEFAULT 'ONGOING';
+ALTER TABLE chess_board ADD COLUMN winner TEXT NULL REFERENCES piece_colors(color);
+ALTER TABLE chess_board ADD COLUMN halfmove_clock INTEGER NOT NULL DEFAULT 0;
+ALTER TABLE chess_board ADD COLUMN position_history TEXT NOT NULL DEFAULT '';
+ALTER TABLE chess_board ADD COLUMN fullmove_number INTEGER NOT NULL DEFAULT 1;
+ALTER TABLE chess_board ADD COLUMN start_fen TEXT NOT NULL DEFAULT 'rnbqkbnr/pppppppp/8/8/8/8/PPPPPPPP/RNBQKBNR w KQkq - 0 1';
+
+CREATE TABLE IF NOT EXISTS board (
+    col TEXT NOT NULL,
+    row INTEGER NOT NULL,
+    field_color TEXT NOT NULL,
+    has_piece INTEGER NOT NULL,
+    piece_color TEXT NULL,
+    piece_name TEXT NULL,
+    FOREIGN KEY (piece_color,piece_name) references pieces(color,name),
+    FOREIGN KEY (col) references cols(col),
+    FOREIGN KEY (row) references rows(row),
+    FOREIGN KEY (field_color) references piece_colors(color),
+    CONSTRAINT board_PK PRIMARY KEY (col,row),
+    CONSTRAINT board_unique UNIQUE (col,row),
+    CONSTRAINT has_piece_booloean CHECK (has_piece=1 OR has_piece=0),
+    CONSTRAINT has_piece_check CHECK (has_piece=0 AND piece_color=NULL AND piece_name=NULL OR has_piece=1 AND piece_color!=NULL AND piece_name!=NULL)
+);
+
+CREATE TABLE IF NOT EXISTS board_counter(
+    id INTEGER NOT NULL PRIMARY KEY AUTOINCREMENT
+);
+INSERT INTO board_counter VALUES (NULL);
+
+CREATE TABLE IF NOT EXISTS board_moves(
+    board_name TEXT NOT NULL,
+    ply INTEGER NOT NULL,
+    from_square TEXT NOT NULL,
+    to_square TEXT NOT NULL,
+    promotion TEXT NULL,
+    san TEXT NOT NULL,
+    FOREIGN KEY (board_name) REFERENCES chess_board(board_name),
+    FOREIGN KEY (promotion) REFERENCES piece_names(name),
+    CONSTRAINT moves_PK PRIMARY KEY (board_name, ply)
+);
+
+CREATE TEMPORARY TABLE first_game AS
+SELECT id FROM games ORDER BY created_at, rowid LIMIT 1;
+
+INSERT INTO chess_board (ID, player_turn, board_name, castling_rights, en_passant, status, winner, halfmove_clock, position_history, fullmove_number, start_fen)
+SELECT NULL, player_turn, 'board', castling_rights, en_passant, status, winner, halfmove_clock, position_history, fullmove_number, start_fen
+FROM games WHERE id IN (SELECT id FROM first_game);
+
+INSERT INTO board (col, row, field_color, has_piece, piece_color, piece_name)
+SELECT col, row, field_color, has_piece, piece_color, piece_name
+FROM squares WHERE game_id IN (SELECT id FROM first_game);
+
+INSERT INTO board_moves (board_name, ply, from_square, to_square, promotion, san)
+SELECT 'board', ply, from_square, to_square, promotion, san
+FROM moves WHERE game_id IN (SELECT id FROM first_game);
+
+DROP TABLE first_game;
+DROP TABLE IF EXISTS moves;
+DROP TABLE IF EXISTS squares;
+DROP TABLE IF EXISTS games;
+
+ALTER TABLE board_moves RENAME TO moves;
@@ -0,0 +1,66 @@
+-- Add up migration script here
+CREATE TABLE IF NOT EXISTS games(
+    id TEXT NOT NULL PRIMARY KEY,
+    player_turn TEXT NOT NULL DEFAULT 'WHITE',
+    castling_rights TEXT NOT NULL DEFAULT 'KQkq',
+    en_passant TEXT NULL,
+    status TEXT NOT NULL DEFAULT 'ONGOING',
+    winner TEXT NULL,
+    halfmove_clock INTEGER NOT NULL DEFAULT 0,
+    position_history TEXT NOT NULL DEFAULT '',
+    fullmove_number INTEGER NOT NULL DEFAULT 1,
+    start_fen TEXT NOT NULL DEFAULT 'rnbqkbnr/pppppppp/8/8/8/8/PPPPPPPP/RNBQKBNR w KQkq - 0 1',
+    created_at TEXT NOT NULL DEFAULT CURRENT_TIMESTAMP,
+    FOREIGN KEY (player_turn) REFERENCES piece_colors(color),
+    FOREIGN KEY (winner) REFERENCES piece_colors(color)
+);
+
+CREATE TABLE IF NOT EXISTS squares(
+    game_id TEXT NOT NULL,
+    col TEXT NOT NULL,
+    row INTEGER NOT NULL,
+    field_color TEXT NOT NULL,
+    has_piece INTEGER NOT NULL,
+    piece_color TEXT NULL,
+    piece_name TEXT NULL,
+    FOREIGN KEY (game_id) REFERENCES games(id) ON DELETE CASCADE,
+    FOREIGN KEY (piece_color,piece_name) REFERENCES pieces(color,name),
+    FOREIGN KEY (col) REFERENCES cols(col),
+    FOREIGN KEY (row) REFERENCES rows(row),
+    FOREIGN KEY (field_color) REFERENCES piece_colors(color),
+    CONSTRAINT squares_PK PRIMARY KEY (game_id,col,row),
+    CONSTRAINT has_piece_boolean CHECK (has_piece=1 OR has_piece=0),
+    CONSTRAINT has_piece_check CHECK (has_piece=0 AND piece_color IS NULL AND piece_name IS NULL OR has_piece=1 AND piece_color IS NOT NULL AND piece_name IS NOT NULL)
+);
+
+CREATE TABLE IF NOT EXISTS game_moves(
+    game_id TEXT NOT NULL,
+    ply INTEGER NOT NULL,
+    from_square TEXT NOT NULL,
+    to_square TEXT NOT NULL,
+    promotion TEXT NULL,
+    san TEXT NOT NULL,
+    FOREIGN KEY (game_id) REFERENCES games(id) ON DELETE CASCADE,
+    FOREIGN KEY (promotion) REFERENCES piece_names(name),
+    CONSTRAINT game_moves_PK PRIMARY KEY (game_id, ply)
+);
+
+-- the single hard-coded board becomes the first game, with a random version 4 uuid
+INSERT INTO games (id, player_turn, castling_rights, en_passant, status, winner, halfmove_clock, position_history, fullmove_number, start_fen)
+SELECT
+    lower(hex(randomblob(4))) || '-' || lower(hex(randomblob(2))) || '-4' || substr(lower(hex(randomblob(2))), 2) || '-' || substr('89ab', 1 + abs(random()) % 4, 1) || substr(lower(hex(randomblob(2))), 2) || '-' || lower(hex(randomblob(6))),
+    player_turn, castling_rights, en_passant, status, winner, halfmove_clock, position_history, fullmove_number, start_fen
+FROM chess_board WHERE board_name = 'board';
+
+INSERT INTO squares (game_id, col, row, field_color, has_piece, piece_color, piece_name)
+SELECT games.id, col, row, field_color, has_piece, piece_color, piece_name FROM board, games;
+
+INSERT INTO game_moves (game_id, ply, from_square, to_square, promotion, san)
+SELECT games.id, ply, from_square, to_square, promotion, san FROM moves, games;
+
+DROP TABLE IF EXISTS moves;
+DROP TABLE IF EXISTS board;
+DROP TABLE IF EXISTS board_counter;
+DROP TABLE IF EXISTS chess_board;
+
+ALTER TABLE game_moves RENAME TO moves;
@@ -0,0 +1,7 @@
+-- Add down migration script here
+ALTER TABLE games DROP COLUMN black_token;
+ALTER TABLE games DROP COLUMN white_token;
+ALTER TABLE games DROP COLUMN rated;
+ALTER TABLE games DROP COLUMN time_increment;
+ALTER TABLE games DROP COLUMN time_initial;
+ALTER TABLE games DROP COLUMN variant;
@@ -0,0 +1,12 @@
+-- Add up migration script here
+ALTER TABLE games ADD COLUMN variant TEXT NOT NULL DEFAULT 'STANDARD';
+ALTER TABLE games ADD COLUMN time_initial INTEGER NULL;
+ALTER TABLE games ADD COLUMN time_increment INTEGER NULL;
+ALTER TABLE games ADD COLUMN rated INTEGER NOT NULL DEFAULT 0 CHECK (rated=1 OR rated=0);
+ALTER TABLE games ADD COLUMN white_token TEXT NULL;
+ALTER TABLE games ADD COLUMN black_token TEXT NULL;
+
+-- games created before the seats existed get a random version 4 uuid for each of them
+UPDATE games SET
+    white_token = lower(hex(randomblob(4))) || '-' || lower(hex(randomblob(2))) || '-4' || substr(lower(hex(randomblob(2))), 2) || '-' || substr('89ab', 1 + abs(random()) % 4, 1) || substr(lower(hex(randomblob(2))), 2) || '-' || lower(hex(randomblob(6))),
+    black_token = lower(hex(randomblob(4))) || '-' || lower(hex(randomblob(2))) || '-4' || substr(lower(hex(randomblob(2))), 2) || '-' || substr('89ab', 1 + abs(random()) % 4, 1) || substr(lower(hex(randomblob(2))), 2) || '-' || lower(hex(randomblob(6)));
@@ -0,0 +1,18 @@
+-- Add down migration script here
+CREATE TABLE IF NOT EXISTS game_moves(
+    game_id TEXT NOT NULL,
+    ply INTEGER NOT NULL,
+    from_square TEXT NOT NULL,
+    to_square TEXT NOT NULL,
+    promotion TEXT NULL,
+    san TEXT NOT NULL,
+    FOREIGN KEY (game_id) REFERENCES games(id) ON DELETE CASCADE,
+    FOREIGN KEY (promotion) REFERENCES piece_names(name),
+    CONSTRAINT game_moves_PK PRIMARY KEY (game_id, ply)
+);
+
+INSERT INTO game_moves (game_id, ply, from_square, to_square, promotion, san)
+SELECT game_id, ply, from_square, to_square, promotion, san FROM moves;
+
+DROP TABLE IF EXISTS moves;
+ALTER TABLE game_moves RENAME TO moves;
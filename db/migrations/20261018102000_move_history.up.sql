@@ -0,0 +1,23 @@
+-- Add up migration script here
+CREATE TABLE IF NOT EXISTS move_history(
+    game_id TEXT NOT NULL,
+    ply INTEGER NOT NULL,
+    from_square TEXT NOT NULL,
+    to_square TEXT NOT NULL,
+    promotion TEXT NULL,
+    san TEXT NOT NULL,
+    captured TEXT NULL,
+    fen_after TEXT NULL,
+    played_at TEXT NOT NULL DEFAULT CURRENT_TIMESTAMP,
+    FOREIGN KEY (game_id) REFERENCES games(id) ON DELETE CASCADE,
+    FOREIGN KEY (promotion) REFERENCES piece_names(name),
+    FOREIGN KEY (captured) REFERENCES piece_names(name),
+    CONSTRAINT move_history_PK PRIMARY KEY (game_id, ply)
+);
+
+-- moves played before the history existed do not know their capture or the position after them
+INSERT INTO move_history (game_id, ply, from_square, to_square, promotion, san)
+SELECT game_id, ply, from_square, to_square, promotion, san FROM moves;
+
+DROP TABLE IF EXISTS moves;
+ALTER TABLE move_history RENAME TO moves;
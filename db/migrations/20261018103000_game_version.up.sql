@@ -0,0 +1,2 @@
+-- Add up migration script here
+ALTER TABLE games ADD COLUMN version INTEGER NOT NULL DEFAULT 0;
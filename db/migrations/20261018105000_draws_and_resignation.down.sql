@@ -0,0 +1,3 @@
+-- Add down migration script here
+DROP TABLE IF EXISTS game_events;
+ALTER TABLE games DROP COLUMN draw_offer_by;
@@ -0,0 +1,14 @@
+-- Add up migration script here
+ALTER TABLE games ADD COLUMN draw_offer_by TEXT NULL;
+
+CREATE TABLE IF NOT EXISTS game_events(
+    id INTEGER PRIMARY KEY AUTOINCREMENT NOT NULL,
+    game_id TEXT NOT NULL,
+    ply INTEGER NOT NULL,
+    color TEXT NOT NULL,
+    event TEXT NOT NULL,
+    created_at TEXT NOT NULL DEFAULT CURRENT_TIMESTAMP,
+    FOREIGN KEY (game_id) REFERENCES games(id) ON DELETE CASCADE,
+    FOREIGN KEY (color) REFERENCES piece_colors(color),
+    CONSTRAINT event_names CHECK (event IN ('DRAW_OFFER', 'DRAW_DECLINE', 'DRAW_ACCEPT', 'RESIGN'))
+);
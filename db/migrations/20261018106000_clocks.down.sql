@@ -0,0 +1,6 @@
+-- Add down migration script here
+ALTER TABLE games DROP COLUMN turn_started_at;
+ALTER TABLE games DROP COLUMN black_ms;
+ALTER TABLE games DROP COLUMN white_ms;
+ALTER TABLE games DROP COLUMN days_per_move;
+ALTER TABLE games DROP COLUMN clock_kind;
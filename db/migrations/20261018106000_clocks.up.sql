@@ -0,0 +1,10 @@
+-- Add up migration script here
+ALTER TABLE games ADD COLUMN clock_kind TEXT NULL;
+ALTER TABLE games ADD COLUMN days_per_move INTEGER NULL;
+ALTER TABLE games ADD COLUMN white_ms INTEGER NULL;
+ALTER TABLE games ADD COLUMN black_ms INTEGER NULL;
+ALTER TABLE games ADD COLUMN turn_started_at INTEGER NULL;
+
+-- games created with a time control so far got a fischer clock that was never started
+UPDATE games SET clock_kind = 'FISCHER', white_ms = time_initial * 1000, black_ms = time_initial * 1000
+WHERE time_initial IS NOT NULL;
@@ -0,0 +1,5 @@
+-- Add down migration script here
+ALTER TABLE games DROP COLUMN black_user_id;
+ALTER TABLE games DROP COLUMN white_user_id;
+DROP TABLE IF EXISTS sessions;
+DROP TABLE IF EXISTS users;
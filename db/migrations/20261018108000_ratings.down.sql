@@ -0,0 +1,5 @@
+-- Add down migration script here
+ALTER TABLE games DROP COLUMN rated_at;
+DROP INDEX IF EXISTS rating_history_user;
+DROP TABLE IF EXISTS rating_history;
+DROP TABLE IF EXISTS ratings;
@@ -0,0 +1,33 @@
+-- Add up migration script here
+CREATE TABLE IF NOT EXISTS ratings(
+    user_id TEXT NOT NULL,
+    pool TEXT NOT NULL,
+    rating REAL NOT NULL,
+    deviation REAL NOT NULL,
+    volatility REAL NOT NULL,
+    games INTEGER NOT NULL DEFAULT 0,
+    updated_at TEXT NOT NULL DEFAULT CURRENT_TIMESTAMP,
+    PRIMARY KEY (user_id, pool),
+    FOREIGN KEY (user_id) REFERENCES users(id) ON DELETE CASCADE,
+    CONSTRAINT rating_pools CHECK (pool IN ('BULLET', 'BLITZ', 'RAPID', 'CLASSICAL', 'CORRESPONDENCE'))
+);
+
+CREATE TABLE IF NOT EXISTS rating_history(
+    id INTEGER PRIMARY KEY AUTOINCREMENT NOT NULL,
+    user_id TEXT NOT NULL,
+    pool TEXT NOT NULL,
+    game_id TEXT NOT NULL,
+    rating_before REAL NOT NULL,
+    rating REAL NOT NULL,
+    deviation REAL NOT NULL,
+    volatility REAL NOT NULL,
+    created_at TEXT NOT NULL DEFAULT CURRENT_TIMESTAMP,
+    FOREIGN KEY (user_id) REFERENCES users(id) ON DELETE CASCADE,
+    FOREIGN KEY (game_id) REFERENCES games(id) ON DELETE CASCADE,
+    CONSTRAINT rating_pools CHECK (pool IN ('BULLET', 'BLITZ', 'RAPID', 'CLASSICAL', 'CORRESPONDENCE'))
+);
+
+CREATE INDEX IF NOT EXISTS rating_history_user ON rating_history(user_id, pool);
+
+-- a game changes the ratings only once, even if it is reset and ends again
+ALTER TABLE games ADD COLUMN rated_at TEXT NULL;
@@ -0,0 +1,3 @@
+-- Add up migration script here
+-- the seat of the player who created the game, games created before it existed have none
+ALTER TABLE games ADD COLUMN creator TEXT NULL REFERENCES piece_colors(color);
@@ -0,0 +1,4 @@
+-- Add down migration script here
+ALTER TABLE moves DROP COLUMN turn_started_at;
+ALTER TABLE moves DROP COLUMN black_ms;
+ALTER TABLE moves DROP COLUMN white_ms;
@@ -0,0 +1,5 @@
+-- Add up migration script here
+-- the clock after the move, so a takeback can put it back. Moves without a clock have none.
+ALTER TABLE moves ADD COLUMN white_ms INTEGER NULL;
+ALTER TABLE moves ADD COLUMN black_ms INTEGER NULL;
+ALTER TABLE moves ADD COLUMN turn_started_at INTEGER NULL;
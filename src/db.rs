@@ -1,8 +1,18 @@
 use std::cell::RefCell;
 use std::collections::HashMap;
 
-use crate::models::{printablePiece, Board, Color, Position, Tile};
-use sqlx::{FromRow, Pool, Row, Sqlite, SqlitePool};
+use crate::live::{Hub, Update};
+use crate::models::{
+    clock::Clock,
+    rating::{self, Rating, RatingPool},
+    user::User,
+    Board, CastlingRights, ClockKind, Color, GameEvent, GameSettings, GameStatus, Move, Piece,
+    Position, PrintablePiece, Tile, TimeControl, Variant, STARTING_FEN,
+};
+use serde::Serialize;
+use sqlx::{sqlite::SqliteRow, FromRow, Pool, Row, Sqlite, SqliteConnection, SqlitePool};
+use std::str::FromStr;
+use uuid::Uuid;
 type Matrix = Vec<Vec<RefCell<Tile>>>;
 use sqlx::migrate::MigrateDatabase;
 const DB_URL: &str = "sqlite://db/chess.db";
@@ -10,7 +20,144 @@ const DB_URL: &str = "sqlite://db/chess.db";
 #[derive(Clone)]
 pub struct DB {
     connection: Pool<Sqlite>,
-    board_name: String,
+    hub: Hub,
+}
+
+#[derive(Debug, Serialize)]
+pub struct PlayerTokens {
+    pub white: Uuid,
+    pub black: Uuid,
+}
+
+//? another request changed the game between reading the board and writing the move
+#[derive(Debug)]
+pub struct Conflict;
+
+impl std::fmt::Display for Conflict {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        write!(
+            f,
+            "the game was changed by another move, reload it and try again"
+        )
+    }
+}
+
+impl std::error::Error for Conflict {}
+
+//? a row of the moves table, captured and fen are unknown for moves stored before they existed
+#[derive(Debug, Serialize)]
+pub struct MoveRecord {
+    pub ply: i64,
+    pub from: String,
+    pub to: String,
+    pub promotion: Option<String>,
+    pub san: String,
+    pub captured: Option<String>,
+    pub fen: Option<String>,
+    pub played_at: String,
+}
+
+#[derive(Debug, Serialize)]
+pub struct EventRecord {
+    pub ply: i64,
+    pub color: String,
+    pub event: String,
+    pub created_at: String,
+}
+
+//? a game as listed for one of its players
+#[derive(Debug, Serialize)]
+pub struct UserGame {
+    pub id: Uuid,
+    pub color: Color,
+    pub status: GameStatus,
+    pub created_at: String,
+}
+
+#[derive(Debug, Serialize)]
+pub struct PoolRating {
+    pub pool: RatingPool,
+    #[serde(flatten)]
+    pub rating: Rating,
+    pub games: i64,
+}
+
+#[derive(Debug, Serialize)]
+pub struct RatingRecord {
+    pub pool: RatingPool,
+    pub game_id: Uuid,
+    pub rating_before: f64,
+    pub rating: f64,
+    pub deviation: f64,
+    pub created_at: String,
+}
+
+#[derive(Debug, Serialize)]
+pub struct LeaderboardEntry {
+    pub rank: i64,
+    pub user_id: Uuid,
+    pub username: String,
+    pub rating: f64,
+    pub deviation: f64,
+    pub games: i64,
+}
+
+#[derive(Debug, Serialize)]
+pub struct ColorStats {
+    pub color: Color,
+    pub games: i64,
+    pub wins: i64,
+    pub losses: i64,
+    pub draws: i64,
+}
+
+#[derive(Debug, Serialize)]
+pub struct OpeningStats {
+    pub moves: String,
+    pub games: i64,
+}
+
+#[derive(Debug, Serialize)]
+pub struct RecentGame {
+    pub id: Uuid,
+    pub color: Color,
+    pub result: String,
+    pub status: GameStatus,
+}
+
+//? counted over the finished games of a user
+#[derive(Debug, Serialize)]
+pub struct UserStats {
+    pub by_color: Vec<ColorStats>,
+    pub average_plies: Option<f64>,
+    pub openings: Vec<OpeningStats>,
+    pub recent: Vec<RecentGame>,
+}
+
+//? the finished games of the user ?1 with the color they played and the result for them
+const FINISHED_GAMES: &str = "select id, status, winner, created_at, rowid as game_order,
+    case when white_user_id =?1 then 'WHITE' else 'BLACK' end as color,
+    case when winner is null then 'draw'
+        when winner = case when white_user_id =?1 then 'WHITE' else 'BLACK' end then 'win'
+        else 'loss' end as result
+    from games where (white_user_id =?1 or black_user_id =?1) and status != 'ONGOING'";
+
+//? how many plies of a game name its opening in the stats
+const OPENING_PLIES: i64 = 4;
+
+//? the accounts sitting at a game, seats taken with a token only have none
+#[derive(Debug, Serialize)]
+pub struct Players {
+    pub white: Option<User>,
+    pub black: Option<User>,
+}
+
+//? a single game in the DB, every query of it is scoped to its id
+#[derive(Clone)]
+pub struct Game {
+    connection: Pool<Sqlite>,
+    hub: Hub,
+    id: Uuid,
 }
 
 impl DB {
@@ -38,15 +185,447 @@ impl DB {
 
         DB {
             connection,
-            board_name: String::from("board"),
+            hub: Hub::default(),
         }
     }
 
-    pub async fn print(&self) -> HashMap<i8, Vec<printablePiece>> {
+    //? stores a new game with the position of the given board,
+    //? each side gets its own token to claim the seat with
+    pub async fn create_game(
+        &self,
+        board: &Board,
+        settings: &GameSettings,
+        creator: Option<&Color>,
+    ) -> std::result::Result<(Game, PlayerTokens), Box<dyn std::error::Error>> {
+        self.insert_game(board, settings, creator, &board.to_fen(), &[])
+            .await
+    }
+
+    //? stores a replayed game so it can be continued, with its moves from the starting position.
+    //? The game and its moves are written together, a failed import leaves nothing behind.
+    pub async fn import_game(
+        &self,
+        start_fen: &str,
+        moves: &[Move],
+        board: &Board,
+        settings: &GameSettings,
+    ) -> std::result::Result<(Game, PlayerTokens), Box<dyn std::error::Error>> {
+        self.insert_game(board, settings, None, start_fen, moves)
+            .await
+    }
+
+    async fn insert_game(
+        &self,
+        board: &Board,
+        settings: &GameSettings,
+        creator: Option<&Color>,
+        start_fen: &str,
+        moves: &[Move],
+    ) -> std::result::Result<(Game, PlayerTokens), Box<dyn std::error::Error>> {
+        let game = Game {
+            connection: self.connection.clone(),
+            hub: self.hub.clone(),
+            id: Uuid::new_v4(),
+        };
+        let tokens = PlayerTokens {
+            white: Uuid::new_v4(),
+            black: Uuid::new_v4(),
+        };
+        let mut tx = self.connection.begin().await?;
+        let game_query =
+            "insert into games (id, variant, time_initial, time_increment, clock_kind, days_per_move, white_ms, black_ms, rated, white_token, black_token, creator) values (?, ?, ?, ?, ?, ?, ?, ?, ?, ?, ?, ?)";
+        let clock = settings.time_control.as_ref().map(Clock::new);
+        sqlx::query(game_query)
+            .bind(game.id.to_string())
+            .bind(settings.variant.as_str())
+            .bind(settings.time_control.as_ref().map(|time| time.initial))
+            .bind(settings.time_control.as_ref().map(|time| time.increment))
+            .bind(
+                settings
+                    .time_control
+                    .as_ref()
+                    .map(|time| time.kind.as_str()),
+            )
+            .bind(
+                settings
+                    .time_control
+                    .as_ref()
+                    .map(|time| time.days_per_move),
+            )
+            .bind(clock.as_ref().map(|clock| clock.white_ms))
+            .bind(clock.as_ref().map(|clock| clock.black_ms))
+            .bind(settings.rated)
+            .bind(tokens.white.to_string())
+            .bind(tokens.black.to_string())
+            .bind(creator.map(|color| color.as_str()))
+            .execute(&mut *tx)
+            .await?;
+        let square_query =
+            "insert into squares (game_id, col, row, field_color, has_piece) values (?, ?, ?, ?, 0)";
+        for row in 0..8 {
+            for col in 0..8 {
+                let pos = Position::new_from_index(row, col);
+                sqlx::query(square_query)
+                    .bind(game.id.to_string())
+                    .bind(String::from(pos.file))
+                    .bind(pos.rank)
+                    .bind(board.get_tile_color(&pos).as_str())
+                    .execute(&mut *tx)
+                    .await?;
+            }
+        }
+        game.replace_game(&mut tx, start_fen, moves, board).await?;
+        tx.commit().await?;
+        Ok((game, tokens))
+    }
+
+    pub async fn get_game(
+        &self,
+        id: &Uuid,
+    ) -> std::result::Result<Option<Game>, Box<dyn std::error::Error>> {
+        let game = sqlx::query("select id from games where id =?")
+            .bind(id.to_string())
+            .fetch_optional(&self.connection)
+            .await?;
+        Ok(game.map(|_| Game {
+            connection: self.connection.clone(),
+            hub: self.hub.clone(),
+            id: *id,
+        }))
+    }
+}
+
+//? how long a login lasts without logging in again
+pub const SESSION_DAYS: i64 = 30;
+
+impl DB {
+    //? None when the username is taken, names are compared case insensitively
+    pub async fn create_user(
+        &self,
+        username: &str,
+        password_hash: &str,
+    ) -> std::result::Result<Option<User>, Box<dyn std::error::Error>> {
+        let user_query = "insert into users (id, username, password_hash) values (?, ?, ?)
+            returning id, username, created_at";
+        let user = sqlx::query(user_query)
+            .bind(Uuid::new_v4().to_string())
+            .bind(username)
+            .bind(password_hash)
+            .fetch_one(&self.connection)
+            .await;
+        match user {
+            Ok(user) => Ok(Some(User::from_row(&user)?)),
+            Err(sqlx::Error::Database(e)) if e.is_unique_violation() => Ok(None),
+            Err(e) => Err(e.into()),
+        }
+    }
+
+    //? the user with the stored password hash to check a login against
+    pub async fn get_login(
+        &self,
+        username: &str,
+    ) -> std::result::Result<Option<(User, String)>, Box<dyn std::error::Error>> {
+        let login_query =
+            "select id, username, created_at, password_hash from users where username =?";
+        let login = sqlx::query(login_query)
+            .bind(username)
+            .fetch_optional(&self.connection)
+            .await?;
+        Ok(match login {
+            Some(login) => Some((User::from_row(&login)?, login.try_get("password_hash")?)),
+            None => None,
+        })
+    }
+
+    pub async fn get_user(
+        &self,
+        id: &Uuid,
+    ) -> std::result::Result<Option<User>, Box<dyn std::error::Error>> {
+        let user = sqlx::query("select id, username, created_at from users where id =?")
+            .bind(id.to_string())
+            .fetch_optional(&self.connection)
+            .await?;
+        Ok(user.as_ref().map(User::from_row).transpose()?)
+    }
+
+    //? the pools the user has played rated games in
+    pub async fn get_ratings(
+        &self,
+        user: &User,
+    ) -> std::result::Result<Vec<PoolRating>, Box<dyn std::error::Error>> {
+        let ratings_query = "select pool, rating, deviation, volatility, games from ratings
+            where user_id =? order by games desc";
+        let ratings = sqlx::query(ratings_query)
+            .bind(user.id.to_string())
+            .fetch_all(&self.connection)
+            .await?;
+        ratings
+            .iter()
+            .map(|row| {
+                Ok(PoolRating {
+                    pool: RatingPool::from_str(row.try_get("pool")?)?,
+                    rating: Rating {
+                        rating: row.try_get("rating")?,
+                        deviation: row.try_get("deviation")?,
+                        volatility: row.try_get("volatility")?,
+                    },
+                    games: row.try_get("games")?,
+                })
+            })
+            .collect()
+    }
+
+    //? the rating after every rated game, oldest first
+    pub async fn get_rating_history(
+        &self,
+        user: &User,
+        pool: Option<&RatingPool>,
+    ) -> std::result::Result<Vec<RatingRecord>, Box<dyn std::error::Error>> {
+        let history_query = "select pool, game_id, rating_before, rating, deviation, created_at
+            from rating_history where user_id =? and (? is null or pool =?) order by id";
+        let history = sqlx::query(history_query)
+            .bind(user.id.to_string())
+            .bind(pool.map(|pool| pool.as_str()))
+            .bind(pool.map(|pool| pool.as_str()))
+            .fetch_all(&self.connection)
+            .await?;
+        history
+            .iter()
+            .map(|row| {
+                Ok(RatingRecord {
+                    pool: RatingPool::from_str(row.try_get("pool")?)?,
+                    game_id: Uuid::parse_str(row.try_get("game_id")?)?,
+                    rating_before: row.try_get("rating_before")?,
+                    rating: row.try_get("rating")?,
+                    deviation: row.try_get("deviation")?,
+                    created_at: row.try_get("created_at")?,
+                })
+            })
+            .collect()
+    }
+
+    //? a page of the players of a pool by rating, with the number of players in it
+    pub async fn get_leaderboard(
+        &self,
+        pool: &RatingPool,
+        page: u32,
+        per_page: u32,
+    ) -> std::result::Result<(i64, Vec<LeaderboardEntry>), Box<dyn std::error::Error>> {
+        let total = sqlx::query("select count(*) as total from ratings where pool =?")
+            .bind(pool.as_str())
+            .fetch_one(&self.connection)
+            .await?
+            .try_get("total")?;
+        let offset = (page.saturating_sub(1) as i64) * per_page as i64;
+        let leaderboard_query =
+            "select users.id, users.username, ratings.rating, ratings.deviation, ratings.games
+            from ratings join users on users.id = ratings.user_id
+            where ratings.pool =? order by ratings.rating desc, ratings.games desc, users.username
+            limit ? offset ?";
+        let players = sqlx::query(leaderboard_query)
+            .bind(pool.as_str())
+            .bind(per_page)
+            .bind(offset)
+            .fetch_all(&self.connection)
+            .await?;
+        let players = players
+            .iter()
+            .enumerate()
+            .map(|(i, row)| {
+                Ok(LeaderboardEntry {
+                    rank: offset + i as i64 + 1,
+                    user_id: Uuid::parse_str(row.try_get("id")?)?,
+                    username: row.try_get("username")?,
+                    rating: row.try_get("rating")?,
+                    deviation: row.try_get("deviation")?,
+                    games: row.try_get("games")?,
+                })
+            })
+            .collect::<std::result::Result<Vec<_>, Box<dyn std::error::Error>>>()?;
+        Ok((total, players))
+    }
+
+    pub async fn get_user_stats(
+        &self,
+        user: &User,
+        recent: u32,
+    ) -> std::result::Result<UserStats, Box<dyn std::error::Error>> {
+        let user_id = user.id.to_string();
+
+        let color_query = format!(
+            "select color, count(*) as games, sum(result = 'win') as wins,
+            sum(result = 'loss') as losses, sum(result = 'draw') as draws
+            from ({FINISHED_GAMES}) group by color order by color desc"
+        );
+        let by_color = sqlx::query(&color_query)
+            .bind(&user_id)
+            .fetch_all(&self.connection)
+            .await?
+            .iter()
+            .map(|row| {
+                Ok(ColorStats {
+                    color: Color::from_row(row)?,
+                    games: row.try_get("games")?,
+                    wins: row.try_get("wins")?,
+                    losses: row.try_get("losses")?,
+                    draws: row.try_get("draws")?,
+                })
+            })
+            .collect::<std::result::Result<Vec<_>, Box<dyn std::error::Error>>>()?;
+
+        let length_query = format!(
+            "select avg(plies) as average_plies from (
+                select (select count(*) from moves where moves.game_id = finished.id) as plies
+                from ({FINISHED_GAMES}) as finished)"
+        );
+        let average_plies = sqlx::query(&length_query)
+            .bind(&user_id)
+            .fetch_one(&self.connection)
+            .await?
+            .try_get("average_plies")?;
+
+        //? the first plies of every finished game from the standard position, pivoted into one line by ply
+        let opening_query = format!(
+            "select moves, count(*) as games from (
+                select trim(max(case when moves.ply = 1 then moves.san end)
+                    || ' ' || coalesce(max(case when moves.ply = 2 then moves.san end), '')
+                    || ' ' || coalesce(max(case when moves.ply = 3 then moves.san end), '')
+                    || ' ' || coalesce(max(case when moves.ply = 4 then moves.san end), '')) as moves
+                from ({FINISHED_GAMES}) as finished
+                join games on games.id = finished.id
+                join moves on moves.game_id = finished.id
+                where games.variant = 'STANDARD' and moves.ply <= ?2
+                group by finished.id)
+            group by moves order by games desc, moves limit 5"
+        );
+        let openings = sqlx::query(&opening_query)
+            .bind(&user_id)
+            .bind(OPENING_PLIES)
+            .fetch_all(&self.connection)
+            .await?
+            .iter()
+            .map(|row| {
+                Ok(OpeningStats {
+                    moves: row.try_get("moves")?,
+                    games: row.try_get("games")?,
+                })
+            })
+            .collect::<std::result::Result<Vec<_>, Box<dyn std::error::Error>>>()?;
+
+        let recent_query = format!(
+            "select * from ({FINISHED_GAMES}) order by created_at desc, game_order desc limit ?2"
+        );
+        let recent = sqlx::query(&recent_query)
+            .bind(&user_id)
+            .bind(recent)
+            .fetch_all(&self.connection)
+            .await?
+            .iter()
+            .map(|row| {
+                Ok(RecentGame {
+                    id: Uuid::parse_str(row.try_get("id")?)?,
+                    color: Color::from_row(row)?,
+                    result: row.try_get("result")?,
+                    status: GameStatus::from_db(row.try_get("status")?, row.try_get("winner")?)?,
+                })
+            })
+            .collect::<std::result::Result<Vec<_>, Box<dyn std::error::Error>>>()?;
+
+        Ok(UserStats {
+            by_color,
+            average_plies,
+            openings,
+            recent,
+        })
+    }
+
+    pub async fn create_session(
+        &self,
+        user: &User,
+    ) -> std::result::Result<Uuid, Box<dyn std::error::Error>> {
+        let token = Uuid::new_v4();
+        let session_query = "insert into sessions (token, user_id, expires_at)
+            values (?, ?, datetime('now', '+' || ? || ' days'))";
+        sqlx::query(session_query)
+            .bind(token.to_string())
+            .bind(user.id.to_string())
+            .bind(SESSION_DAYS)
+            .execute(&self.connection)
+            .await?;
+        Ok(token)
+    }
+
+    //? expired sessions are treated like unknown ones
+    pub async fn get_session_user(
+        &self,
+        token: &Uuid,
+    ) -> std::result::Result<Option<User>, Box<dyn std::error::Error>> {
+        let session_query = "select users.id, users.username, users.created_at from sessions
+            join users on users.id = sessions.user_id
+            where sessions.token =? and sessions.expires_at > datetime('now')";
+        let user = sqlx::query(session_query)
+            .bind(token.to_string())
+            .fetch_optional(&self.connection)
+            .await?;
+        Ok(user.as_ref().map(User::from_row).transpose()?)
+    }
+
+    pub async fn delete_session(
+        &self,
+        token: &Uuid,
+    ) -> std::result::Result<(), Box<dyn std::error::Error>> {
+        sqlx::query("delete from sessions where token =? or expires_at <= datetime('now')")
+            .bind(token.to_string())
+            .execute(&self.connection)
+            .await?;
+        Ok(())
+    }
+
+    //? the newest games first
+    pub async fn get_user_games(
+        &self,
+        user: &User,
+    ) -> std::result::Result<Vec<UserGame>, Box<dyn std::error::Error>> {
+        let games_query =
+            "select id, case when white_user_id =? then 'WHITE' else 'BLACK' end as color,
+            status, winner, created_at from games
+            where white_user_id =? or black_user_id =?
+            order by created_at desc, rowid desc";
+        let games = sqlx::query(games_query)
+            .bind(user.id.to_string())
+            .bind(user.id.to_string())
+            .bind(user.id.to_string())
+            .fetch_all(&self.connection)
+            .await?;
+        games
+            .iter()
+            .map(|game| {
+                Ok(UserGame {
+                    id: Uuid::parse_str(game.try_get("id")?)?,
+                    color: Color::from_row(game)?,
+                    status: GameStatus::from_db(game.try_get("status")?, game.try_get("winner")?)?,
+                    created_at: game.try_get("created_at")?,
+                })
+            })
+            .collect()
+    }
+}
+
+impl Game {
+    pub fn id(&self) -> &Uuid {
+        &self.id
+    }
+
+    //? every committed change of the game is announced to its subscribers
+    pub fn subscribe(&self) -> tokio::sync::broadcast::Receiver<Update> {
+        self.hub.subscribe(&self.id)
+    }
+
+    pub async fn print(&self) -> HashMap<i8, Vec<PrintablePiece>> {
         let board_query =
-            "select col,row,symbol,field_color,piece_color from board left join pieces on (color,name) = (piece_color,piece_name);"
-                .replace("board", &self.board_name);
-        let board = sqlx::query(&board_query)
+            "select col,row,symbol,field_color,piece_color from squares left join pieces on (color,name) = (piece_color,piece_name) where game_id =? order by row, col;";
+        let board = sqlx::query(board_query)
+            .bind(self.id.to_string())
             .fetch_all(&self.connection)
             .await
             .unwrap();
@@ -57,7 +636,7 @@ impl DB {
             res.insert(i, Vec::with_capacity(8));
         }
         for r in board {
-            let piece = printablePiece::from_row(&r).unwrap();
+            let piece = PrintablePiece::from_row(&r).unwrap();
 
             res.get_mut(&piece.row).unwrap().push(piece);
         }
@@ -66,22 +645,42 @@ impl DB {
     }
 
     pub async fn get_board(&self) -> Board {
-        let chess_board_query = "select player_turn from chess_board where board_name =?;";
-        let player_turn = sqlx::query(chess_board_query)
-            .bind(&self.board_name)
-            .fetch_one(&self.connection)
+        self.get_versioned_board().await.0
+    }
+
+    //? the board together with the version it was read at, both from the same snapshot
+    pub async fn get_versioned_board(&self) -> (Board, i64) {
+        let mut tx = self.connection.begin().await.unwrap();
+        let game_query =
+            "select player_turn, castling_rights, en_passant, status, winner, halfmove_clock, position_history, fullmove_number, version from games where id =?;";
+        let game = sqlx::query(game_query)
+            .bind(self.id.to_string())
+            .fetch_one(&mut *tx)
             .await
             .unwrap();
-        let board_query = "select field_color,piece_color as color ,piece_name as name,range,row  from board left join pieces ON (color,name)=(piece_color,piece_name);".replace("board", &self.board_name);
-        let pieces = sqlx::query(&board_query)
-            .fetch_all(&self.connection)
+        let board_query = "select field_color,piece_color as color ,piece_name as name,range,row  from squares left join pieces ON (color,name)=(piece_color,piece_name) where game_id =? order by row, col;";
+        let pieces = sqlx::query(board_query)
+            .bind(self.id.to_string())
+            .fetch_all(&mut *tx)
             .await
             .unwrap();
-        let player_turn = match player_turn.try_get("player_turn") {
+        tx.commit().await.unwrap();
+        let player_turn = match game.try_get("player_turn") {
             Ok("WHITE") => Color::White,
             Ok("BLACK") => Color::Black,
             _ => panic!("Invalid player_turn_color"),
         };
+        let castling = CastlingRights::from_str(game.get("castling_rights")).unwrap();
+        let en_passant = game
+            .get::<Option<&str>, _>("en_passant")
+            .map(|pos| Position::from_str(pos).unwrap());
+        let status = GameStatus::from_db(game.get("status"), game.get("winner")).unwrap();
+        let history = game
+            .get::<&str, _>("position_history")
+            .split(';')
+            .filter(|key| !key.is_empty())
+            .map(String::from)
+            .collect();
         let mut res: Matrix = Vec::with_capacity(8);
         for _ in 1..=8 {
             res.push(Vec::with_capacity(8));
@@ -96,84 +695,809 @@ impl DB {
                 _ => panic!("Unexpected row"),
             });
 
-        Board {
+        let board = Board {
             board: res,
             players_turn: player_turn,
-        }
+            castling,
+            en_passant,
+            status,
+            halfmove_clock: game.get("halfmove_clock"),
+            history,
+            fullmove_number: game.get("fullmove_number"),
+        };
+        (board, game.get("version"))
     }
 
+    //? persists the move and the state after it in one transaction, so a failing query
+    //? can not leave the board half moved. The move was computed on the board at the expected
+    //? version, if another request changed the game since then nothing is written.
     pub async fn move_piece(
         &self,
-        from: Position,
-        to: Position,
+        moved: &Move,
+        board: &Board,
+        clock: Option<&Clock>,
+        expected_version: i64,
+    ) -> std::result::Result<i64, Box<dyn std::error::Error>> {
+        let mut tx = self.connection.begin().await?;
+        self.claim_version(&mut tx, expected_version).await?;
+        if let Some(clock) = clock {
+            self.save_clock(&mut tx, clock).await?;
+        }
+        self.relocate_piece(&mut tx, &moved.from, &moved.to).await?;
+        if let Some((rook_from, rook_to)) = &moved.castling_rook {
+            self.relocate_piece(&mut tx, rook_from, rook_to).await?;
+        }
+        if let Some(captured) = &moved.en_passant_capture {
+            self.clear_tile(&mut tx, captured).await?;
+        }
+        if let Some(promotion) = &moved.promotion {
+            let promotion_query =
+                "update squares set piece_name =? where game_id =? and row =? and col =?";
+            sqlx::query(promotion_query)
+                .bind(promotion.as_str())
+                .bind(self.id.to_string())
+                .bind(moved.to.rank)
+                .bind(String::from(moved.to.file))
+                .execute(&mut *tx)
+                .await?;
+        }
+        self.record_move(&mut tx, moved, clock).await?;
+        self.save_board_state(&mut tx, board).await?;
+        if board.status != GameStatus::Ongoing {
+            self.rate_game(&mut tx, &board.status).await?;
+        }
+        tx.commit().await?;
+        self.hub.publish(&self.id, Update::Move);
+
+        Ok(expected_version + 1)
+    }
+
+    //? undoes the last plies, the board has to be the position before them
+    pub async fn take_back(
+        &self,
+        board: &Board,
+        plies: i64,
+        clock: Option<&Clock>,
+        expected_version: i64,
+    ) -> std::result::Result<i64, Box<dyn std::error::Error>> {
+        let mut tx = self.connection.begin().await?;
+        self.claim_version(&mut tx, expected_version).await?;
+        if let Some(clock) = clock {
+            self.save_clock(&mut tx, clock).await?;
+        }
+        let take_back_query =
+            "delete from moves where game_id =? and ply > (select count(*) from moves where game_id =?) - ?";
+        sqlx::query(take_back_query)
+            .bind(self.id.to_string())
+            .bind(self.id.to_string())
+            .bind(plies)
+            .execute(&mut *tx)
+            .await?;
+        //? draw offers and the like made after the undone moves go with them
+        let events_query = "delete from game_events where game_id =? and ply > (select count(*) from moves where game_id =?)";
+        sqlx::query(events_query)
+            .bind(self.id.to_string())
+            .bind(self.id.to_string())
+            .execute(&mut *tx)
+            .await?;
+        self.write_tiles(&mut tx, board).await?;
+        self.save_board_state(&mut tx, board).await?;
+        tx.commit().await?;
+        self.hub.publish(&self.id, Update::TakeBack);
+
+        Ok(expected_version + 1)
+    }
+
+    //? ends the game without a move, by resignation, agreement or the flag falling.
+    //? The event is left out when the game ends without a player doing something.
+    pub async fn end_game(
+        &self,
+        status: &GameStatus,
+        event: Option<(&GameEvent, &Color)>,
+        clock: Option<&Clock>,
+        expected_version: i64,
+    ) -> std::result::Result<i64, Box<dyn std::error::Error>> {
+        let mut tx = self.connection.begin().await?;
+        self.claim_version(&mut tx, expected_version).await?;
+        sqlx::query("update games set status =?, winner =? where id =?")
+            .bind(status.as_str())
+            .bind(status.winner().map(|color| color.as_str()))
+            .bind(self.id.to_string())
+            .execute(&mut *tx)
+            .await?;
+        if let Some(clock) = clock {
+            self.save_clock(&mut tx, clock).await?;
+        }
+        if let Some((event, color)) = event {
+            self.record_event(&mut tx, event, color).await?;
+        }
+        self.rate_game(&mut tx, status).await?;
+        tx.commit().await?;
+        self.hub.publish(&self.id, Update::Status);
+
+        Ok(expected_version + 1)
+    }
+
+    //? an offer of None withdraws or declines the pending one, like a takeback offer it only
+    //? lands on the game at the expected version
+    pub async fn set_draw_offer(
+        &self,
+        offer: Option<&Color>,
+        event: &GameEvent,
+        color: &Color,
+        expected_version: i64,
+    ) -> std::result::Result<i64, Box<dyn std::error::Error>> {
+        let mut tx = self.connection.begin().await?;
+        self.claim_version(&mut tx, expected_version).await?;
+        sqlx::query("update games set draw_offer_by =? where id =?")
+            .bind(offer.map(|color| color.as_str()))
+            .bind(self.id.to_string())
+            .execute(&mut *tx)
+            .await?;
+        self.record_event(&mut tx, event, color).await?;
+        tx.commit().await?;
+        self.hub.publish(&self.id, Update::DrawOffer);
+        Ok(expected_version + 1)
+    }
+
+    pub async fn get_draw_offer(
+        &self,
+    ) -> std::result::Result<Option<Color>, Box<dyn std::error::Error>> {
+        let offer = sqlx::query("select draw_offer_by from games where id =?;")
+            .bind(self.id.to_string())
+            .fetch_one(&self.connection)
+            .await?;
+        Ok(offer
+            .try_get::<Option<&str>, _>("draw_offer_by")?
+            .map(Color::from_str)
+            .transpose()?)
+    }
+
+    //? the event is stored at the ply it happened after
+    async fn record_event(
+        &self,
+        conn: &mut SqliteConnection,
+        event: &GameEvent,
+        color: &Color,
     ) -> std::result::Result<(), Box<dyn std::error::Error>> {
-        let from_piece = "select piece_color, piece_name from board where row =? and col =?";
+        let event_query = "insert into game_events (game_id, ply, color, event)
+            select ?, count(*), ?, ? from moves where game_id =?";
+        sqlx::query(event_query)
+            .bind(self.id.to_string())
+            .bind(color.as_str())
+            .bind(event.as_str())
+            .bind(self.id.to_string())
+            .execute(&mut *conn)
+            .await?;
+        Ok(())
+    }
+
+    pub async fn get_events(
+        &self,
+    ) -> std::result::Result<Vec<EventRecord>, Box<dyn std::error::Error>> {
+        let events_query =
+            "select ply, color, event, created_at from game_events where game_id =? order by id";
+        let events = sqlx::query(events_query)
+            .bind(self.id.to_string())
+            .fetch_all(&self.connection)
+            .await?;
+        Ok(events
+            .iter()
+            .map(|row| EventRecord {
+                ply: row.get("ply"),
+                color: row.get("color"),
+                event: row.get("event"),
+                created_at: row.get("created_at"),
+            })
+            .collect())
+    }
+
+    //? None for games without a time control
+    pub async fn get_clock(
+        &self,
+    ) -> std::result::Result<Option<(TimeControl, Clock)>, Box<dyn std::error::Error>> {
+        let clock_query = "select clock_kind, time_initial, time_increment, days_per_move, white_ms, black_ms, turn_started_at from games where id =?;";
+        let clock = sqlx::query(clock_query)
+            .bind(self.id.to_string())
+            .fetch_one(&self.connection)
+            .await?;
+        let time_control = match read_time_control(&clock)? {
+            Some(time_control) => time_control,
+            None => return Ok(None),
+        };
+        let clock = Clock {
+            white_ms: clock.try_get("white_ms")?,
+            black_ms: clock.try_get("black_ms")?,
+            turn_started_at: clock.try_get("turn_started_at")?,
+        };
+        Ok(Some((time_control, clock)))
+    }
+
+    //? moves the ratings of both accounts once a rated game has ended, in the pool of its
+    //? time control. Rated games only start with an account at both seats.
+    async fn rate_game(
+        &self,
+        conn: &mut SqliteConnection,
+        status: &GameStatus,
+    ) -> std::result::Result<(), Box<dyn std::error::Error>> {
+        let white_score = match rating::white_score(status) {
+            Some(score) => score,
+            None => return Ok(()),
+        };
+        let game_query = "select rated, rated_at, white_user_id, black_user_id, clock_kind, time_initial, time_increment, days_per_move from games where id =?";
+        let game = sqlx::query(game_query)
+            .bind(self.id.to_string())
+            .fetch_one(&mut *conn)
+            .await?;
+        if !game.try_get::<bool, _>("rated")?
+            || game.try_get::<Option<&str>, _>("rated_at")?.is_some()
+        {
+            return Ok(());
+        }
+        let (white, black) = match (
+            game.try_get::<Option<String>, _>("white_user_id")?,
+            game.try_get::<Option<String>, _>("black_user_id")?,
+        ) {
+            (Some(white), Some(black)) if white != black => (white, black),
+            _ => return Ok(()),
+        };
+        let pool = match read_time_control(&game)? {
+            Some(time_control) => RatingPool::of(&time_control),
+            None => return Ok(()),
+        };
+
+        let white_rating = read_rating(conn, &white, &pool).await?;
+        let black_rating = read_rating(conn, &black, &pool).await?;
+        let new_white = white_rating.update(&black_rating, white_score);
+        let new_black = black_rating.update(&white_rating, 1.0 - white_score);
+        self.save_rating(conn, &white, &pool, &white_rating, &new_white)
+            .await?;
+        self.save_rating(conn, &black, &pool, &black_rating, &new_black)
+            .await?;
+        sqlx::query("update games set rated_at = CURRENT_TIMESTAMP where id =?")
+            .bind(self.id.to_string())
+            .execute(&mut *conn)
+            .await?;
+        Ok(())
+    }
+
+    async fn save_rating(
+        &self,
+        conn: &mut SqliteConnection,
+        user_id: &str,
+        pool: &RatingPool,
+        before: &Rating,
+        after: &Rating,
+    ) -> std::result::Result<(), Box<dyn std::error::Error>> {
+        let rating_query =
+            "insert into ratings (user_id, pool, rating, deviation, volatility, games)
+            values (?, ?, ?, ?, ?, 1)
+            on conflict (user_id, pool) do update set rating = excluded.rating,
+            deviation = excluded.deviation, volatility = excluded.volatility,
+            games = games + 1, updated_at = CURRENT_TIMESTAMP";
+        sqlx::query(rating_query)
+            .bind(user_id)
+            .bind(pool.as_str())
+            .bind(after.rating)
+            .bind(after.deviation)
+            .bind(after.volatility)
+            .execute(&mut *conn)
+            .await?;
+        let history_query = "insert into rating_history (user_id, pool, game_id, rating_before, rating, deviation, volatility)
+            values (?, ?, ?, ?, ?, ?, ?)";
+        sqlx::query(history_query)
+            .bind(user_id)
+            .bind(pool.as_str())
+            .bind(self.id.to_string())
+            .bind(before.rating)
+            .bind(after.rating)
+            .bind(after.deviation)
+            .bind(after.volatility)
+            .execute(&mut *conn)
+            .await?;
+        Ok(())
+    }
+
+    async fn save_clock(
+        &self,
+        conn: &mut SqliteConnection,
+        clock: &Clock,
+    ) -> std::result::Result<(), Box<dyn std::error::Error>> {
+        sqlx::query("update games set white_ms =?, black_ms =?, turn_started_at =? where id =?")
+            .bind(clock.white_ms)
+            .bind(clock.black_ms)
+            .bind(clock.turn_started_at)
+            .bind(self.id.to_string())
+            .execute(&mut *conn)
+            .await?;
+        Ok(())
+    }
+
+    //? bumps the version if it is still the expected one, every change of the game invalidates
+    //? a pending takeback or draw offer. The update comes first so the transaction holds the write lock
+    //? before anything is read.
+    async fn claim_version(
+        &self,
+        conn: &mut SqliteConnection,
+        expected_version: i64,
+    ) -> std::result::Result<(), Box<dyn std::error::Error>> {
+        let version_query =
+            "update games set version = version + 1, takeback_by = NULL, draw_offer_by = NULL where id =? and version =?";
+        let updated = sqlx::query(version_query)
+            .bind(self.id.to_string())
+            .bind(expected_version)
+            .execute(&mut *conn)
+            .await?;
+        if updated.rows_affected() == 0 {
+            return Err(Box::new(Conflict));
+        }
+        Ok(())
+    }
+
+    //? the clock after the move is kept with it, a takeback puts it back
+    async fn record_move(
+        &self,
+        conn: &mut SqliteConnection,
+        moved: &Move,
+        clock: Option<&Clock>,
+    ) -> std::result::Result<(), Box<dyn std::error::Error>> {
+        let record_move_query =
+            "insert into moves (game_id, ply, from_square, to_square, promotion, san, captured, fen_after, white_ms, black_ms, turn_started_at)
+            select ?, count(*) + 1, ?, ?, ?, ?, ?, ?, ?, ?, ? from moves where game_id =?";
+        sqlx::query(record_move_query)
+            .bind(self.id.to_string())
+            .bind(moved.from.to_string())
+            .bind(moved.to.to_string())
+            .bind(moved.promotion.as_ref().map(|promotion| promotion.as_str()))
+            .bind(&moved.san)
+            .bind(moved.captured.as_ref().map(|piece| piece.name()))
+            .bind(&moved.fen)
+            .bind(clock.map(|clock| clock.white_ms))
+            .bind(clock.map(|clock| clock.black_ms))
+            .bind(clock.and_then(|clock| clock.turn_started_at))
+            .bind(self.id.to_string())
+            .execute(&mut *conn)
+            .await?;
+        Ok(())
+    }
+
+    pub async fn get_moves(
+        &self,
+    ) -> std::result::Result<Vec<MoveRecord>, Box<dyn std::error::Error>> {
+        let moves_query =
+            "select ply, from_square, to_square, promotion, san, captured, fen_after, played_at from moves where game_id =? order by ply";
+        let moves = sqlx::query(moves_query)
+            .bind(self.id.to_string())
+            .fetch_all(&self.connection)
+            .await?;
+        Ok(moves
+            .iter()
+            .map(|row| MoveRecord {
+                ply: row.get("ply"),
+                from: row.get("from_square"),
+                to: row.get("to_square"),
+                promotion: row.get("promotion"),
+                san: row.get("san"),
+                captured: row.get("captured"),
+                fen: row.get("fen_after"),
+                played_at: row.get("played_at"),
+            })
+            .collect())
+    }
+
+    //? the clock as it was right after the given ply, None for moves stored without one
+    pub async fn get_clock_after(
+        &self,
+        ply: i64,
+    ) -> std::result::Result<Option<Clock>, Box<dyn std::error::Error>> {
+        let clock_query =
+            "select white_ms, black_ms, turn_started_at from moves where game_id =? and ply =?";
+        let clock = sqlx::query(clock_query)
+            .bind(self.id.to_string())
+            .bind(ply)
+            .fetch_optional(&self.connection)
+            .await?;
+        let clock = match clock {
+            Some(clock) => clock,
+            None => return Ok(None),
+        };
+        match (
+            clock.try_get::<Option<i64>, _>("white_ms")?,
+            clock.try_get::<Option<i64>, _>("black_ms")?,
+        ) {
+            (Some(white_ms), Some(black_ms)) => Ok(Some(Clock {
+                white_ms,
+                black_ms,
+                turn_started_at: clock.try_get("turn_started_at")?,
+            })),
+            _ => Ok(None),
+        }
+    }
+
+    pub async fn get_san_moves(
+        &self,
+    ) -> std::result::Result<Vec<String>, Box<dyn std::error::Error>> {
+        let moves_query = "select san from moves where game_id =? order by ply";
+        let moves = sqlx::query(moves_query)
+            .bind(self.id.to_string())
+            .fetch_all(&self.connection)
+            .await?;
+        Ok(moves.iter().map(|row| row.get("san")).collect())
+    }
+
+    pub async fn get_start_fen(&self) -> std::result::Result<String, Box<dyn std::error::Error>> {
+        let start_fen_query = "select start_fen from games where id =?;";
+        let start_fen = sqlx::query(start_fen_query)
+            .bind(self.id.to_string())
+            .fetch_one(&self.connection)
+            .await?;
+        Ok(start_fen.try_get("start_fen")?)
+    }
+
+    //? overwrites every tile and the state of the game with the given board,
+    //? the move list starts over from the loaded position and the variant follows it
+    pub async fn load_board(
+        &self,
+        board: &Board,
+        expected_version: i64,
+    ) -> std::result::Result<i64, Box<dyn std::error::Error>> {
+        let start_fen = board.to_fen();
+        let mut tx = self.connection.begin().await?;
+        self.claim_version(&mut tx, expected_version).await?;
+        sqlx::query("update games set variant =? where id =?")
+            .bind(Variant::of(&start_fen).as_str())
+            .bind(self.id.to_string())
+            .execute(&mut *tx)
+            .await?;
+        self.replace_game(&mut tx, &start_fen, &[], board).await?;
+        tx.commit().await?;
+        self.hub.publish(&self.id, Update::Position);
+        Ok(expected_version + 1)
+    }
+
+    async fn replace_game(
+        &self,
+        conn: &mut SqliteConnection,
+        start_fen: &str,
+        moves: &[Move],
+        board: &Board,
+    ) -> std::result::Result<(), Box<dyn std::error::Error>> {
+        //? a new position starts with full clocks that wait for the first moves again
+        let clock_query = "update games set
+            white_ms = case clock_kind when 'CORRESPONDENCE' then days_per_move * 86400000 else time_initial * 1000 end,
+            black_ms = case clock_kind when 'CORRESPONDENCE' then days_per_move * 86400000 else time_initial * 1000 end,
+            turn_started_at = NULL
+            where id =? and clock_kind is not null";
+        sqlx::query(clock_query)
+            .bind(self.id.to_string())
+            .execute(&mut *conn)
+            .await?;
+        self.write_tiles(conn, board).await?;
+        self.save_board_state(conn, board).await?;
+        self.start_move_list(conn, start_fen).await?;
+        for moved in moves {
+            self.record_move(conn, moved, None).await?;
+        }
+        Ok(())
+    }
+
+    async fn write_tiles(
+        &self,
+        conn: &mut SqliteConnection,
+        board: &Board,
+    ) -> std::result::Result<(), Box<dyn std::error::Error>> {
+        let tile_query =
+            "update squares set has_piece =?, piece_color =?, piece_name =? where game_id =? and row =? and col =?";
+        for row in 0..8 {
+            for col in 0..8 {
+                let pos = Position::new_from_index(row, col);
+                let piece = board.get_piece(&pos);
+                sqlx::query(tile_query)
+                    .bind(piece.is_some())
+                    .bind(piece.as_ref().map(|piece| piece.get_color().as_str()))
+                    .bind(piece.as_ref().map(|piece| piece.name()))
+                    .bind(self.id.to_string())
+                    .bind(pos.rank)
+                    .bind(String::from(pos.file))
+                    .execute(&mut *conn)
+                    .await?;
+            }
+        }
+        Ok(())
+    }
+
+    async fn start_move_list(
+        &self,
+        conn: &mut SqliteConnection,
+        start_fen: &str,
+    ) -> std::result::Result<(), Box<dyn std::error::Error>> {
+        sqlx::query("delete from moves where game_id =?")
+            .bind(self.id.to_string())
+            .execute(&mut *conn)
+            .await?;
+        sqlx::query("delete from game_events where game_id =?")
+            .bind(self.id.to_string())
+            .execute(&mut *conn)
+            .await?;
+        sqlx::query("update games set start_fen =? where id =?")
+            .bind(start_fen)
+            .bind(self.id.to_string())
+            .execute(&mut *conn)
+            .await?;
+        Ok(())
+    }
+
+    async fn relocate_piece(
+        &self,
+        conn: &mut SqliteConnection,
+        from: &Position,
+        to: &Position,
+    ) -> std::result::Result<(), Box<dyn std::error::Error>> {
+        let from_piece =
+            "select piece_color, piece_name from squares where game_id =? and row =? and col =?";
         let from_piece = sqlx::query(from_piece)
+            .bind(self.id.to_string())
             .bind(from.rank)
             .bind(String::from(from.file))
-            .fetch_one(&self.connection)
+            .fetch_one(&mut *conn)
             .await?;
         let from_piece_name: String = from_piece.try_get("piece_name")?;
         let from_piece_color: String = from_piece.try_get("piece_color")?;
-        let empty_from_piece =
-            "update board set has_piece=0, piece_color =NULL, piece_name =NULL where row =? and col =?";
-        sqlx::query(empty_from_piece)
-            .bind(from.rank)
-            .bind(String::from(from.file))
-            .execute(&self.connection)
-            .await?;
-        let move_query = "update board set piece_color =?, piece_name =? where row =? and col =?";
+        self.clear_tile(conn, from).await?;
+        let move_query =
+            "update squares set has_piece=1, piece_color =?, piece_name =? where game_id =? and row =? and col =?";
         sqlx::query(move_query)
             .bind(from_piece_color)
             .bind(from_piece_name)
+            .bind(self.id.to_string())
             .bind(to.rank)
             .bind(String::from(to.file))
-            .execute(&self.connection)
+            .execute(&mut *conn)
             .await?;
-        self.change_player_turn().await?;
+        Ok(())
+    }
 
+    async fn clear_tile(
+        &self,
+        conn: &mut SqliteConnection,
+        pos: &Position,
+    ) -> std::result::Result<(), Box<dyn std::error::Error>> {
+        let empty_piece =
+            "update squares set has_piece=0, piece_color =NULL, piece_name =NULL where game_id =? and row =? and col =?";
+        sqlx::query(empty_piece)
+            .bind(self.id.to_string())
+            .bind(pos.rank)
+            .bind(String::from(pos.file))
+            .execute(&mut *conn)
+            .await?;
         Ok(())
     }
 
-    pub async fn change_player_turn(&self) -> std::result::Result<(), Box<dyn std::error::Error>> {
-        let player_turn = self.get_player_turn().await?;
-        let new_player_turn = match player_turn {
-            Color::White => "BLACK",
-            Color::Black => "WHITE",
-        };
-        let change_player_turn_query = "update chess_board set player_turn =? where board_name =?";
+    //? stores everything besides the tiles that is needed to continue the game
+    async fn save_board_state(
+        &self,
+        conn: &mut SqliteConnection,
+        board: &Board,
+    ) -> std::result::Result<(), Box<dyn std::error::Error>> {
+        let save_state_query =
+            "update games set player_turn =?, castling_rights =?, en_passant =?, status =?, winner =?, halfmove_clock =?, position_history =?, fullmove_number =? where id =?";
 
-        sqlx::query(change_player_turn_query)
-            .bind(new_player_turn)
-            .bind(&self.board_name)
-            .execute(&self.connection)
+        sqlx::query(save_state_query)
+            .bind(board.players_turn.as_str())
+            .bind(board.castling.to_string())
+            .bind(board.en_passant.as_ref().map(|pos| pos.to_string()))
+            .bind(board.status.as_str())
+            .bind(board.status.winner().map(|color| color.as_str()))
+            .bind(board.halfmove_clock)
+            .bind(board.history.join(";"))
+            .bind(board.fullmove_number)
+            .bind(self.id.to_string())
+            .execute(&mut *conn)
             .await?;
         Ok(())
     }
 
+    //? the color whose seat the token belongs to
+    pub async fn get_seat(
+        &self,
+        token: &Uuid,
+    ) -> std::result::Result<Option<Color>, Box<dyn std::error::Error>> {
+        let seat_query = "select white_token, black_token from games where id =?;";
+        let seats = sqlx::query(seat_query)
+            .bind(self.id.to_string())
+            .fetch_one(&self.connection)
+            .await?;
+        let token = Some(token.to_string());
+        if seats.try_get::<Option<String>, _>("white_token")? == token {
+            return Ok(Some(Color::White));
+        }
+        if seats.try_get::<Option<String>, _>("black_token")? == token {
+            return Ok(Some(Color::Black));
+        }
+        Ok(None)
+    }
+
+    //? links the seat to the account, false when another account already sits there
+    pub async fn claim_seat(
+        &self,
+        color: &Color,
+        user: &User,
+    ) -> std::result::Result<bool, Box<dyn std::error::Error>> {
+        let seat_query = match color {
+            Color::White => "update games set white_user_id =? where id =? and (white_user_id is null or white_user_id =?)",
+            Color::Black => "update games set black_user_id =? where id =? and (black_user_id is null or black_user_id =?)",
+        };
+        let claimed = sqlx::query(seat_query)
+            .bind(user.id.to_string())
+            .bind(self.id.to_string())
+            .bind(user.id.to_string())
+            .execute(&self.connection)
+            .await?;
+        Ok(claimed.rows_affected() == 1)
+    }
+
+    pub async fn get_players(&self) -> std::result::Result<Players, Box<dyn std::error::Error>> {
+        let players_query = "select white_user_id, black_user_id from games where id =?";
+        let players = sqlx::query(players_query)
+            .bind(self.id.to_string())
+            .fetch_one(&self.connection)
+            .await?;
+        let mut seats = Vec::with_capacity(2);
+        for column in ["white_user_id", "black_user_id"] {
+            let user = match players.try_get::<Option<&str>, _>(column)? {
+                Some(id) => sqlx::query("select id, username, created_at from users where id =?")
+                    .bind(id)
+                    .fetch_optional(&self.connection)
+                    .await?
+                    .as_ref()
+                    .map(User::from_row)
+                    .transpose()?,
+                None => None,
+            };
+            seats.push(user);
+        }
+        let black = seats.pop().flatten();
+        let white = seats.pop().flatten();
+        Ok(Players { white, black })
+    }
+
+    pub async fn get_takeback_offer(
+        &self,
+    ) -> std::result::Result<Option<Color>, Box<dyn std::error::Error>> {
+        let offer = sqlx::query("select takeback_by from games where id =?;")
+            .bind(self.id.to_string())
+            .fetch_one(&self.connection)
+            .await?;
+        Ok(offer
+            .try_get::<Option<&str>, _>("takeback_by")?
+            .map(Color::from_str)
+            .transpose()?)
+    }
+
+    //? an offer of None withdraws or declines the pending one. The offer was checked against
+    //? the game at the expected version, so it can not land on a game that ended since then.
+    pub async fn set_takeback_offer(
+        &self,
+        color: Option<&Color>,
+        expected_version: i64,
+    ) -> std::result::Result<i64, Box<dyn std::error::Error>> {
+        let mut tx = self.connection.begin().await?;
+        self.claim_version(&mut tx, expected_version).await?;
+        sqlx::query("update games set takeback_by =? where id =?")
+            .bind(color.map(|color| color.as_str()))
+            .bind(self.id.to_string())
+            .execute(&mut *tx)
+            .await?;
+        tx.commit().await?;
+        self.hub.publish(&self.id, Update::TakebackOffer);
+        Ok(expected_version + 1)
+    }
+
+    pub async fn get_version(&self) -> std::result::Result<i64, Box<dyn std::error::Error>> {
+        let version = sqlx::query("select version from games where id =?;")
+            .bind(self.id.to_string())
+            .fetch_one(&self.connection)
+            .await?;
+        Ok(version.try_get("version")?)
+    }
+
     pub async fn get_player_turn(&self) -> std::result::Result<Color, Box<dyn std::error::Error>> {
-        let get_player_turn_query =
-            "select player_turn as color from chess_board where board_name =?;";
+        let get_player_turn_query = "select player_turn as color from games where id =?;";
         let player_turn = sqlx::query(get_player_turn_query)
-            .bind(&self.board_name)
+            .bind(self.id.to_string())
             .fetch_one(&self.connection)
             .await?;
         let player_turn: Color = Color::from_row(&player_turn)?;
         Ok(player_turn)
     }
 
-    pub async fn reset(&self) -> Result<(), Box<dyn std::error::Error>> {
-        sqlx::query("DROP TABLE IF EXISTS board;")
-            .execute(&self.connection)
+    pub async fn get_status(&self) -> std::result::Result<GameStatus, Box<dyn std::error::Error>> {
+        let get_status_query = "select status, winner from games where id =?;";
+        let status = sqlx::query(get_status_query)
+            .bind(self.id.to_string())
+            .fetch_one(&self.connection)
             .await?;
-        let query = std::fs::read_to_string("db/migrations/20230809135952_board.up.sql").unwrap();
-        sqlx::query(&query).execute(&self.connection).await?;
-        sqlx::query("UPDATE chess_board SET player_turn = 'WHITE' WHERE board_name = ?;")
-            .bind(&self.board_name)
-            .execute(&self.connection)
+        let status = GameStatus::from_db(status.try_get("status")?, status.try_get("winner")?)?;
+        Ok(status)
+    }
+
+    //? puts the pieces back to the starting position and forgets the moves
+    pub async fn reset(&self, expected_version: i64) -> Result<i64, Box<dyn std::error::Error>> {
+        let board = Board::from_fen(STARTING_FEN)?;
+        self.load_board(&board, expected_version).await
+    }
+
+    //? the seat of the player who created the game, None for imported games
+    pub async fn get_creator(
+        &self,
+    ) -> std::result::Result<Option<Color>, Box<dyn std::error::Error>> {
+        let creator = sqlx::query("select creator from games where id =?;")
+            .bind(self.id.to_string())
+            .fetch_one(&self.connection)
             .await?;
-        Ok(())
+        Ok(creator
+            .try_get::<Option<&str>, _>("creator")?
+            .map(Color::from_str)
+            .transpose()?)
     }
+
+    //? when the game was created, as "YYYY-MM-DD HH:MM:SS" in UTC
+    pub async fn get_created_at(&self) -> std::result::Result<String, Box<dyn std::error::Error>> {
+        let created_at = sqlx::query("select created_at from games where id =?;")
+            .bind(self.id.to_string())
+            .fetch_one(&self.connection)
+            .await?;
+        Ok(created_at.try_get("created_at")?)
+    }
+
+    pub async fn is_rated(&self) -> std::result::Result<bool, Box<dyn std::error::Error>> {
+        let rated = sqlx::query("select rated from games where id =?;")
+            .bind(self.id.to_string())
+            .fetch_one(&self.connection)
+            .await?;
+        Ok(rated.try_get("rated")?)
+    }
+}
+
+//? reads the time control columns of a games row, None for games without one
+fn read_time_control(
+    row: &SqliteRow,
+) -> std::result::Result<Option<TimeControl>, Box<dyn std::error::Error>> {
+    let kind = match row.try_get::<Option<&str>, _>("clock_kind")? {
+        Some(kind) => ClockKind::from_str(kind)?,
+        None => return Ok(None),
+    };
+    Ok(Some(TimeControl {
+        kind,
+        initial: row.try_get::<Option<u32>, _>("time_initial")?.unwrap_or(0),
+        increment: row
+            .try_get::<Option<u32>, _>("time_increment")?
+            .unwrap_or(0),
+        days_per_move: row.try_get::<Option<u32>, _>("days_per_move")?.unwrap_or(0),
+    }))
+}
+
+//? players start at the default rating in every pool they have not played in yet
+async fn read_rating(
+    conn: &mut SqliteConnection,
+    user_id: &str,
+    pool: &RatingPool,
+) -> std::result::Result<Rating, Box<dyn std::error::Error>> {
+    let rating_query =
+        "select rating, deviation, volatility from ratings where user_id =? and pool =?";
+    let rating = sqlx::query(rating_query)
+        .bind(user_id)
+        .bind(pool.as_str())
+        .fetch_optional(&mut *conn)
+        .await?;
+    Ok(match rating {
+        Some(rating) => Rating {
+            rating: rating.try_get("rating")?,
+            deviation: rating.try_get("deviation")?,
+            volatility: rating.try_get("volatility")?,
+        },
+        None => Rating::default(),
+    })
 }
 
 async fn db_migrate() -> Pool<Sqlite> {
@@ -0,0 +1,46 @@
+use serde::Serialize;
+use std::collections::HashMap;
+use std::sync::{Arc, Mutex};
+use tokio::sync::broadcast;
+use uuid::Uuid;
+
+//? how many updates a slow listener may fall behind before it misses some
+const CHANNEL_CAPACITY: usize = 16;
+
+//? what changed in a game, listeners read the new state from the DB themselves
+#[derive(Debug, Clone, Copy, PartialEq, Serialize)]
+#[serde(rename_all = "snake_case")]
+pub enum Update {
+    Move,
+    TakeBack,
+    TakebackOffer,
+    DrawOffer,
+    Status,
+    Position,
+}
+
+//? a broadcast channel per watched game, games nobody watches have none
+#[derive(Clone, Default)]
+pub struct Hub {
+    channels: Arc<Mutex<HashMap<Uuid, broadcast::Sender<Update>>>>,
+}
+
+impl Hub {
+    pub fn subscribe(&self, id: &Uuid) -> broadcast::Receiver<Update> {
+        let mut channels = self.channels.lock().unwrap();
+        channels
+            .entry(*id)
+            .or_insert_with(|| broadcast::channel(CHANNEL_CAPACITY).0)
+            .subscribe()
+    }
+
+    pub fn publish(&self, id: &Uuid, update: Update) {
+        let mut channels = self.channels.lock().unwrap();
+        if let Some(sender) = channels.get(id) {
+            //? sending only fails when every listener is gone, the channel goes with them
+            if sender.send(update).is_err() {
+                channels.remove(id);
+            }
+        }
+    }
+}
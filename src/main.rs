@@ -1,4 +1,5 @@
 mod db;
+mod live;
 //mod filters;
 mod models;
 use actix_cors::Cors;
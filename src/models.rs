@@ -1,9 +1,12 @@
 use serde::{Deserialize, Serialize};
+pub mod clock;
+pub mod pgn;
 mod pieces;
+pub mod rating;
+pub mod user;
 use pieces::{Bishop, King, Knight, Pawn, Queen, Rook};
 use sqlx::{sqlite::SqliteRow, FromRow, Row};
 use std::{
-    borrow::BorrowMut,
     cell::RefCell,
     collections::HashSet,
     fmt::Display,
@@ -12,7 +15,7 @@ use std::{
 };
 
 #[derive(Debug, Serialize)]
-pub struct printablePiece {
+pub struct PrintablePiece {
     pub col: String,
     pub row: i8,
     pub symbol: String,
@@ -20,7 +23,7 @@ pub struct printablePiece {
     pub piece_color: String,
 }
 
-impl FromRow<'_, SqliteRow> for printablePiece {
+impl FromRow<'_, SqliteRow> for PrintablePiece {
     fn from_row(r: &SqliteRow) -> Result<Self, sqlx::Error> {
         Ok(Self {
             col: r.try_get("col")?,
@@ -76,23 +79,25 @@ impl Position {
         };
         Position::new(file, (row + 1) as u8)
     }
+
+    //? returns None when the shifted position would leave the board
+    pub fn offset(&self, file_delta: i8, rank_delta: i8) -> Option<Position> {
+        let file = self.file as i8 + file_delta;
+        let rank = self.rank as i8 + rank_delta;
+        if (b'a' as i8..=b'h' as i8).contains(&file) && (1..=8).contains(&rank) {
+            return Some(Position::new(file as u8 as char, rank as u8));
+        }
+        None
+    }
 }
 
 impl FromStr for Position {
     type Err = String;
+    //? counts characters instead of bytes, client input like "é" is two bytes but one character
     fn from_str(s: &str) -> Result<Self, Self::Err> {
-        if s.len() == 2 {
-            let s = s.to_lowercase();
-            let mut it = s.chars().map(|c| c as u32);
-            let f = it.next().unwrap();
-            let r = it.next().unwrap();
-            if let file @ 97..=104 = f {
-                if let rank @ 49..=56 = r {
-                    let file = char::from_u32(file).unwrap();
-                    let rank = char::from_u32(rank).unwrap().to_digit(10).unwrap() as u8;
-                    return Ok(Position::new(file, rank));
-                }
-            }
+        let chars = s.to_lowercase().chars().collect::<Vec<char>>();
+        if let [file @ 'a'..='h', rank @ '1'..='8'] = chars.as_slice() {
+            return Ok(Position::new(*file, *rank as u8 - b'0'));
         }
 
         Err(String::from("not able to deserialize Position"))
@@ -100,161 +105,1096 @@ impl FromStr for Position {
 }
 
 pub type Db = Arc<Mutex<Board>>;
+pub const STARTING_FEN: &str = "rnbqkbnr/pppppppp/8/8/8/8/PPPPPPPP/RNBQKBNR w KQkq - 0 1";
+const KNIGHT_OFFSETS: [(i8, i8); 8] = [
+    (1, 2),
+    (2, 1),
+    (2, -1),
+    (1, -2),
+    (-1, -2),
+    (-2, -1),
+    (-2, 1),
+    (-1, 2),
+];
+const ORTHOGONAL_DIRECTIONS: [(i8, i8); 4] = [(1, 0), (-1, 0), (0, 1), (0, -1)];
+const DIAGONAL_DIRECTIONS: [(i8, i8); 4] = [(1, 1), (1, -1), (-1, 1), (-1, -1)];
 type Matrix = Vec<Vec<RefCell<Tile>>>;
 #[derive(Clone, Serialize)]
 pub struct Board {
     pub board: Matrix,
     pub players_turn: Color,
+    pub castling: CastlingRights,
+    pub en_passant: Option<Position>,
+    pub status: GameStatus,
+    pub halfmove_clock: u32,
+    //? keys of the positions since the last pawn move or capture, used for repetitions
+    pub history: Vec<String>,
+    pub fullmove_number: u32,
+}
+
+//? which castles are still available, stored like the castling field of a FEN ("KQkq")
+#[derive(Debug, Clone, PartialEq, Serialize)]
+pub struct CastlingRights {
+    pub white_king_side: bool,
+    pub white_queen_side: bool,
+    pub black_king_side: bool,
+    pub black_queen_side: bool,
+}
+
+impl Default for CastlingRights {
+    fn default() -> Self {
+        CastlingRights {
+            white_king_side: true,
+            white_queen_side: true,
+            black_king_side: true,
+            black_queen_side: true,
+        }
+    }
+}
+
+impl CastlingRights {
+    pub fn none() -> Self {
+        CastlingRights {
+            white_king_side: false,
+            white_queen_side: false,
+            black_king_side: false,
+            black_queen_side: false,
+        }
+    }
+
+    pub fn king_side(&self, color: &Color) -> bool {
+        match color {
+            Color::White => self.white_king_side,
+            Color::Black => self.black_king_side,
+        }
+    }
+
+    pub fn queen_side(&self, color: &Color) -> bool {
+        match color {
+            Color::White => self.white_queen_side,
+            Color::Black => self.black_queen_side,
+        }
+    }
+
+    //? a move from or to a king or rook starting square means that piece has moved or was taken
+    fn update(&mut self, from: &Position, to: &Position) {
+        for pos in [from, to] {
+            match (pos.file, pos.rank) {
+                ('e', 1) => {
+                    self.white_king_side = false;
+                    self.white_queen_side = false;
+                }
+                ('e', 8) => {
+                    self.black_king_side = false;
+                    self.black_queen_side = false;
+                }
+                ('h', 1) => self.white_king_side = false,
+                ('a', 1) => self.white_queen_side = false,
+                ('h', 8) => self.black_king_side = false,
+                ('a', 8) => self.black_queen_side = false,
+                _ => {}
+            }
+        }
+    }
+}
+
+impl Display for CastlingRights {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        let mut res = String::new();
+        for (allowed, symbol) in [
+            (self.white_king_side, 'K'),
+            (self.white_queen_side, 'Q'),
+            (self.black_king_side, 'k'),
+            (self.black_queen_side, 'q'),
+        ] {
+            if allowed {
+                res.push(symbol);
+            }
+        }
+        if res.is_empty() {
+            res.push('-');
+        }
+        f.write_str(&res)
+    }
+}
+
+impl FromStr for CastlingRights {
+    type Err = String;
+    fn from_str(s: &str) -> Result<Self, Self::Err> {
+        let mut rights = CastlingRights::none();
+        if s == "-" {
+            return Ok(rights);
+        }
+        for c in s.chars() {
+            match c {
+                'K' => rights.white_king_side = true,
+                'Q' => rights.white_queen_side = true,
+                'k' => rights.black_king_side = true,
+                'q' => rights.black_queen_side = true,
+                _ => return Err(format!("invalid castling rights {}", s)),
+            }
+        }
+        Ok(rights)
+    }
+}
+
+//? everything that changed on the board with a single move, used to persist it
+#[derive(Debug, Clone, Serialize)]
+pub struct Move {
+    pub from: Position,
+    pub to: Position,
+    pub castling_rook: Option<(Position, Position)>,
+    pub en_passant_capture: Option<Position>,
+    pub promotion: Option<Promotion>,
+    pub captured: Option<GameObject>,
+    pub san: String,
+    //? the position after the move
+    pub fen: String,
+}
+
+//? the pieces a pawn can turn into when reaching the last rank
+#[derive(Debug, Clone, PartialEq, Serialize, Deserialize)]
+#[serde(rename_all = "lowercase")]
+pub enum Promotion {
+    Queen,
+    Rook,
+    Bishop,
+    Knight,
+}
+
+impl Promotion {
+    fn to_game_object(&self, color: Color) -> GameObject {
+        match self {
+            Promotion::Queen => GameObject::Queen(Queen::new(color)),
+            Promotion::Rook => GameObject::Rook(Rook::new(color)),
+            Promotion::Bishop => GameObject::Bishop(Bishop::new(color)),
+            Promotion::Knight => GameObject::Knight(Knight::new(color)),
+        }
+    }
+
+    //? the representation used in the piece_names table
+    pub fn as_str(&self) -> &'static str {
+        match self {
+            Promotion::Queen => "QUEEN",
+            Promotion::Rook => "ROOK",
+            Promotion::Bishop => "BISHOP",
+            Promotion::Knight => "KNIGHT",
+        }
+    }
+
+    pub fn from_symbol(symbol: char) -> Option<Promotion> {
+        match symbol.to_ascii_uppercase() {
+            'Q' => Some(Promotion::Queen),
+            'R' => Some(Promotion::Rook),
+            'B' => Some(Promotion::Bishop),
+            'N' => Some(Promotion::Knight),
+            _ => None,
+        }
+    }
+
+    pub fn fen_symbol(&self) -> char {
+        match self {
+            Promotion::Queen => 'Q',
+            Promotion::Rook => 'R',
+            Promotion::Bishop => 'B',
+            Promotion::Knight => 'N',
+        }
+    }
+}
+
+//? from_position games start from a custom FEN instead of the standard setup
+#[derive(Debug, Clone, PartialEq, Serialize, Deserialize)]
+#[serde(rename_all = "snake_case")]
+pub enum Variant {
+    Standard,
+    FromPosition,
+}
+
+impl Variant {
+    pub fn as_str(&self) -> &'static str {
+        match self {
+            Variant::Standard => "STANDARD",
+            Variant::FromPosition => "FROM_POSITION",
+        }
+    }
+
+    //? the variant of a game that starts from the given FEN
+    pub fn of(start_fen: &str) -> Self {
+        if start_fen == STARTING_FEN {
+            Variant::Standard
+        } else {
+            Variant::FromPosition
+        }
+    }
+}
+
+//? how the time of a move is charged, for bronstein the increment is the delay
+#[derive(Debug, Clone, Default, PartialEq, Serialize, Deserialize)]
+#[serde(rename_all = "snake_case")]
+pub enum ClockKind {
+    #[default]
+    Fischer,
+    Bronstein,
+    Correspondence,
+}
+
+impl ClockKind {
+    pub fn as_str(&self) -> &'static str {
+        match self {
+            ClockKind::Fischer => "FISCHER",
+            ClockKind::Bronstein => "BRONSTEIN",
+            ClockKind::Correspondence => "CORRESPONDENCE",
+        }
+    }
+}
+
+impl FromStr for ClockKind {
+    type Err = String;
+    fn from_str(s: &str) -> Result<Self, Self::Err> {
+        match s {
+            "FISCHER" => Ok(ClockKind::Fischer),
+            "BRONSTEIN" => Ok(ClockKind::Bronstein),
+            "CORRESPONDENCE" => Ok(ClockKind::Correspondence),
+            kind => Err(format!("invalid clock kind {}", kind)),
+        }
+    }
+}
+
+//? initial and increment in seconds, correspondence games only use the days per move.
+//? No time control means the game is played without a clock.
+#[derive(Debug, Clone, PartialEq, Serialize, Deserialize)]
+pub struct TimeControl {
+    #[serde(default)]
+    pub kind: ClockKind,
+    #[serde(default)]
+    pub initial: u32,
+    #[serde(default)]
+    pub increment: u32,
+    #[serde(default)]
+    pub days_per_move: u32,
+}
+
+impl TimeControl {
+    pub fn validate(&self) -> Result<(), String> {
+        match self.kind {
+            ClockKind::Correspondence if self.days_per_move == 0 => Err(String::from(
+                "correspondence games need at least one day per move",
+            )),
+            ClockKind::Correspondence => Ok(()),
+            _ if self.initial == 0 && self.increment == 0 => Err(String::from(
+                "the time control needs an initial time or an increment",
+            )),
+            _ => Ok(()),
+        }
+    }
+}
+
+//? everything that is chosen when a game is created and stays fixed afterwards
+#[derive(Debug, Clone, Serialize)]
+pub struct GameSettings {
+    pub variant: Variant,
+    pub time_control: Option<TimeControl>,
+    pub rated: bool,
+}
+
+impl Default for GameSettings {
+    fn default() -> Self {
+        GameSettings {
+            variant: Variant::Standard,
+            time_control: None,
+            rated: false,
+        }
+    }
+}
+
+//? the state of the game after the last move, once it is not ongoing no more moves are accepted
+#[derive(Debug, Clone, PartialEq, Serialize)]
+#[serde(tag = "state", rename_all = "snake_case")]
+pub enum GameStatus {
+    Ongoing,
+    Checkmate { winner: Color },
+    Stalemate,
+    Draw { reason: DrawReason },
+    Resignation { winner: Color },
+    Timeout { winner: Color },
+}
+
+#[derive(Debug, Clone, PartialEq, Serialize)]
+#[serde(rename_all = "snake_case")]
+pub enum DrawReason {
+    FiftyMoveRule,
+    ThreefoldRepetition,
+    InsufficientMaterial,
+    Agreement,
+    //? the flag fell but the opponent could never have checkmated
+    TimeoutVsInsufficientMaterial,
+}
+
+//? what a player did besides moving, kept next to the moves of the game
+#[derive(Debug, Clone, PartialEq, Serialize)]
+#[serde(rename_all = "snake_case")]
+pub enum GameEvent {
+    DrawOffer,
+    DrawDecline,
+    DrawAccept,
+    Resign,
+}
+
+impl GameEvent {
+    pub fn as_str(&self) -> &'static str {
+        match self {
+            GameEvent::DrawOffer => "DRAW_OFFER",
+            GameEvent::DrawDecline => "DRAW_DECLINE",
+            GameEvent::DrawAccept => "DRAW_ACCEPT",
+            GameEvent::Resign => "RESIGN",
+        }
+    }
+}
+
+impl GameStatus {
+    //? the representation used in the status column of chess_board
+    pub fn as_str(&self) -> &'static str {
+        match self {
+            GameStatus::Ongoing => "ONGOING",
+            GameStatus::Checkmate { .. } => "CHECKMATE",
+            GameStatus::Stalemate => "STALEMATE",
+            GameStatus::Draw {
+                reason: DrawReason::FiftyMoveRule,
+            } => "FIFTY_MOVE_RULE",
+            GameStatus::Draw {
+                reason: DrawReason::ThreefoldRepetition,
+            } => "THREEFOLD_REPETITION",
+            GameStatus::Draw {
+                reason: DrawReason::InsufficientMaterial,
+            } => "INSUFFICIENT_MATERIAL",
+            GameStatus::Draw {
+                reason: DrawReason::Agreement,
+            } => "AGREEMENT",
+            GameStatus::Draw {
+                reason: DrawReason::TimeoutVsInsufficientMaterial,
+            } => "TIMEOUT_VS_INSUFFICIENT_MATERIAL",
+            GameStatus::Resignation { .. } => "RESIGNATION",
+            GameStatus::Timeout { .. } => "TIMEOUT",
+        }
+    }
+
+    pub fn winner(&self) -> Option<Color> {
+        match self {
+            GameStatus::Checkmate { winner }
+            | GameStatus::Resignation { winner }
+            | GameStatus::Timeout { winner } => Some(winner.clone()),
+            _ => None,
+        }
+    }
+
+    //? the result as written in PGN
+    pub fn result(&self) -> &'static str {
+        match self {
+            GameStatus::Ongoing => "*",
+            GameStatus::Checkmate {
+                winner: Color::White,
+            }
+            | GameStatus::Resignation {
+                winner: Color::White,
+            }
+            | GameStatus::Timeout {
+                winner: Color::White,
+            } => "1-0",
+            GameStatus::Checkmate {
+                winner: Color::Black,
+            }
+            | GameStatus::Resignation {
+                winner: Color::Black,
+            }
+            | GameStatus::Timeout {
+                winner: Color::Black,
+            } => "0-1",
+            GameStatus::Stalemate | GameStatus::Draw { .. } => "1/2-1/2",
+        }
+    }
+
+    pub fn from_db(status: &str, winner: Option<&str>) -> Result<Self, String> {
+        let winner = winner.map(Color::from_str).transpose()?;
+        match (status, winner) {
+            ("ONGOING", None) => Ok(GameStatus::Ongoing),
+            ("CHECKMATE", Some(winner)) => Ok(GameStatus::Checkmate { winner }),
+            ("STALEMATE", None) => Ok(GameStatus::Stalemate),
+            ("FIFTY_MOVE_RULE", None) => Ok(GameStatus::Draw {
+                reason: DrawReason::FiftyMoveRule,
+            }),
+            ("THREEFOLD_REPETITION", None) => Ok(GameStatus::Draw {
+                reason: DrawReason::ThreefoldRepetition,
+            }),
+            ("INSUFFICIENT_MATERIAL", None) => Ok(GameStatus::Draw {
+                reason: DrawReason::InsufficientMaterial,
+            }),
+            ("AGREEMENT", None) => Ok(GameStatus::Draw {
+                reason: DrawReason::Agreement,
+            }),
+            ("TIMEOUT_VS_INSUFFICIENT_MATERIAL", None) => Ok(GameStatus::Draw {
+                reason: DrawReason::TimeoutVsInsufficientMaterial,
+            }),
+            ("RESIGNATION", Some(winner)) => Ok(GameStatus::Resignation { winner }),
+            ("TIMEOUT", Some(winner)) => Ok(GameStatus::Timeout { winner }),
+            (status, _) => Err(format!("invalid game status {}", status)),
+        }
+    }
 }
 
 impl Board {
-    pub fn move_piece(&mut self, start: &Position, end: &Position) -> Result<(), String> {
-        let mut game_over = false;
-        if self
-            .get_tile(start)
-            .borrow_mut()
-            .piece
-            .as_mut()
-            .unwrap()
-            .get_color()
-            != self.players_turn
-        {
-            return Err(format!("{:?} to play!", self.players_turn));
+    pub fn move_piece(
+        &mut self,
+        start: &Position,
+        end: &Position,
+        promotion: Option<Promotion>,
+    ) -> Result<Move, String> {
+        if self.status != GameStatus::Ongoing {
+            return Err(String::from("the game is already over"));
         }
-        let moves = match self.get_tile(start).borrow_mut().piece.as_mut() {
-            Some(val) => val.get_moves(start, self),
+        let piece = match self.get_piece(start) {
+            Some(piece) => piece,
             None => {
                 return Err(format!("There is no piece at {:?}", start));
             }
         };
-        let before_move_check = self.is_check().is_some();
-        if moves.contains(end) {
-            let mut tile = self.get_tile(end).borrow_mut();
-            let piece = self.get_tile(start).borrow_mut().piece.take().unwrap();
-
-            //todo add_piece probably should not return anything
-            let _ = tile.add_piece(piece);
-
-            //? this scenario should never happen
-            /* if let Some(GameObject::King(_)) = taken_piece {
-                println!("CHECKMATEEEEEEEEE, {:?} WINS!", self.players_turn);
-                game_over = true;
-            } */
-        } else {
+        if piece.get_color() != self.players_turn {
+            return Err(format!("{:?} to play!", self.players_turn));
+        }
+        let moves = self.get_legal_moves(start);
+        if !moves.contains(end) {
+            if self.get_moves_of_piece(start).contains(end) {
+                return Err(String::from("illegal move, your king would be in check"));
+            }
             return Err(String::from("illegal move, piece cant move there"));
         }
-        if before_move_check && self.is_check().is_some() {
-            return Err(format!("it's still check"));
+        match (self.is_promotion(start, end), &promotion) {
+            (true, None) => {
+                return Err(String::from(
+                    "a pawn reaching the last rank has to be promoted",
+                ));
+            }
+            (false, Some(_)) => {
+                return Err(String::from(
+                    "only a pawn reaching the last rank can be promoted",
+                ));
+            }
+            _ => {}
+        }
+
+        //? pawn moves and captures can not be undone, so no earlier position can repeat
+        let is_irreversible = matches!(piece, GameObject::Pawn(_)) || self.get_piece(end).is_some();
+        let previous_position = self.position_key();
+        let san = self.to_san(start, end, promotion.as_ref());
+        let mut moved = self.apply_move(start, end, promotion);
+        moved.san = san;
+        if is_irreversible {
+            self.halfmove_clock = 0;
+            self.history.clear();
+        } else {
+            self.halfmove_clock += 1;
+            self.history.push(previous_position);
+        }
+        if self.players_turn == Color::White {
+            self.fullmove_number += 1;
         }
+        self.status = self.evaluate_status();
+        moved.fen = self.to_fen();
+        Ok(moved)
+    }
+
+    //? moves the piece without validating it, the move has to come from get_moves
+    fn apply_move(
+        &mut self,
+        start: &Position,
+        end: &Position,
+        promotion: Option<Promotion>,
+    ) -> Move {
+        let piece = self.get_piece(start).unwrap();
+
+        //? a king moving two files is castling, the rook jumps over to the other side of the king
+        let mut castling_rook = None;
+        if let GameObject::King(_) = piece {
+            if (start.file as i8 - end.file as i8).abs() == 2 {
+                let (rook_file, rook_target) = if end.file == 'g' {
+                    ('h', 'f')
+                } else {
+                    ('a', 'd')
+                };
+                castling_rook = Some((
+                    Position::new(rook_file, start.rank),
+                    Position::new(rook_target, start.rank),
+                ));
+            }
+        }
+
+        //? a pawn moving diagonally onto an empty tile takes the pawn that just passed it
+        let mut en_passant_capture = None;
+        let mut en_passant_piece = None;
+        let mut en_passant = None;
+        if let GameObject::Pawn(_) = piece {
+            let captured = Position::new(end.file, start.rank);
+            let passed_pawn =
+                self.get_piece_of_color(&captured, &piece.get_color().opposite_color());
+            if start.file != end.file
+                && self.get_piece(end).is_none()
+                && matches!(passed_pawn, Some(GameObject::Pawn(_)))
+            {
+                en_passant_piece = self.get_tile(&captured).borrow_mut().piece.take();
+                en_passant_capture = Some(captured);
+            }
+            if (start.rank as i8 - end.rank as i8).abs() == 2 {
+                en_passant = Some(Position::new(start.file, (start.rank + end.rank) / 2));
+            }
+        }
+
+        let captured = self.relocate_piece(start, end).or(en_passant_piece);
+        if let Some((rook_from, rook_to)) = &castling_rook {
+            self.relocate_piece(rook_from, rook_to);
+        }
+        if let Some(promotion) = &promotion {
+            self.get_tile(end).borrow_mut().piece =
+                Some(promotion.to_game_object(piece.get_color()));
+        }
+        self.castling.update(start, end);
+        self.en_passant = en_passant;
         self.next_turn();
-        if game_over {
-            self.reset_board();
+
+        Move {
+            from: start.clone(),
+            to: end.clone(),
+            castling_rook,
+            en_passant_capture,
+            promotion,
+            captured,
+            san: String::new(),
+            fen: String::new(),
         }
-        Ok(())
     }
 
-    fn reset_board(&mut self) {
-        let clean_board = create_game();
-        self.board = clean_board.lock().unwrap().board.clone();
-        self.players_turn = Color::White;
+    //? finds the legal move of the side to move that is written as the given SAN,
+    //? a missing capture sign, extra disambiguation or a missing check mark are tolerated
+    pub fn parse_san(&self, san: &str) -> Result<(Position, Position, Option<Promotion>), String> {
+        let wanted = normalize_san(san);
+        let illegal = || format!("{} is not a legal move", san);
+        let legal_moves = self.get_all_legal_moves(&self.players_turn);
+
+        if wanted == "O-O" || wanted == "O-O-O" {
+            let rank = match self.players_turn {
+                Color::White => 1,
+                Color::Black => 8,
+            };
+            let from = Position::new('e', rank);
+            let to = Position::new(if wanted == "O-O" { 'g' } else { 'c' }, rank);
+            let is_king = matches!(self.get_piece(&from), Some(GameObject::King(_)));
+            if is_king && legal_moves.contains(&(from.clone(), to.clone())) {
+                return Ok((from, to, None));
+            }
+            return Err(illegal());
+        }
+
+        let (symbol, rest) = match wanted.chars().next() {
+            Some(symbol @ ('K' | 'Q' | 'R' | 'B' | 'N')) => (symbol, &wanted[1..]),
+            Some(_) => ('P', wanted.as_str()),
+            None => return Err(String::from("no move given")),
+        };
+        let mut rest = rest.chars().filter(|c| *c != 'x').collect::<Vec<char>>();
+
+        //? the promotion piece follows the target rank, e.g. e8Q or e8q
+        let mut promotion = None;
+        if rest.len() >= 3 && rest[rest.len() - 2].is_ascii_digit() {
+            if let Some(piece) = Promotion::from_symbol(rest[rest.len() - 1]) {
+                promotion = Some(piece);
+                rest.pop();
+            }
+        }
+        if rest.len() < 2 {
+            return Err(illegal());
+        }
+        let target = rest
+            .split_off(rest.len() - 2)
+            .into_iter()
+            .collect::<String>();
+        let to = Position::from_str(&target).map_err(|_| illegal())?;
+
+        let mut from_file = None;
+        let mut from_rank = None;
+        for c in rest {
+            match c {
+                'a'..='h' => from_file = Some(c),
+                '1'..='8' => from_rank = c.to_digit(10).map(|rank| rank as u8),
+                _ => return Err(illegal()),
+            }
+        }
+
+        let candidates = legal_moves
+            .into_iter()
+            .filter(|(from, move_to)| {
+                *move_to == to
+                    && from_file.unwrap_or(from.file) == from.file
+                    && from_rank.unwrap_or(from.rank) == from.rank
+                    && self
+                        .get_piece(from)
+                        .map(|piece| piece.fen_symbol().to_ascii_uppercase())
+                        == Some(symbol)
+            })
+            .collect::<Vec<(Position, Position)>>();
+
+        match candidates.as_slice() {
+            [(from, to)] => match (self.is_promotion(from, to), promotion) {
+                (true, None) => Err(format!("{} needs a piece to promote to", san)),
+                (false, Some(_)) => Err(illegal()),
+                (_, promotion) => Ok((from.clone(), to.clone(), promotion)),
+            },
+            [] => Err(illegal()),
+            _ => Err(format!("{} is ambiguous", san)),
+        }
     }
 
-    fn next_turn(&mut self) {
-        match self.players_turn {
-            Color::Black => {
-                self.players_turn = Color::White;
+    //? the standard algebraic notation of a legal move, e.g. Nbd7, exd6, O-O-O or e8=Q+
+    pub fn to_san(&self, from: &Position, to: &Position, promotion: Option<&Promotion>) -> String {
+        let piece = match self.get_piece(from) {
+            Some(piece) => piece,
+            None => return String::new(),
+        };
+        let is_capture = self.get_piece(to).is_some();
+        let mut san = String::new();
+        match &piece {
+            GameObject::King(_) if (from.file as i8 - to.file as i8).abs() == 2 => {
+                san.push_str(if to.file == 'g' { "O-O" } else { "O-O-O" });
             }
-            Color::White => {
-                self.players_turn = Color::Black;
+            GameObject::Pawn(_) => {
+                if from.file != to.file {
+                    san.push(from.file);
+                    san.push('x');
+                }
+                san.push_str(&to.to_string());
+                if let Some(promotion) = promotion {
+                    san.push('=');
+                    san.push(promotion.fen_symbol());
+                }
+            }
+            _ => {
+                san.push(piece.fen_symbol().to_ascii_uppercase());
+                //? other pieces of the same kind that could also move there
+                let others = self
+                    .get_all_legal_moves(&piece.get_color())
+                    .into_iter()
+                    .filter(|(other, other_to)| {
+                        other != from
+                            && other_to == to
+                            && self.get_piece(other).map(|p| p.fen_symbol())
+                                == Some(piece.fen_symbol())
+                    })
+                    .map(|(other, _)| other)
+                    .collect::<Vec<Position>>();
+                if !others.is_empty() {
+                    if others.iter().all(|other| other.file != from.file) {
+                        san.push(from.file);
+                    } else if others.iter().all(|other| other.rank != from.rank) {
+                        san.push_str(&from.rank.to_string());
+                    } else {
+                        san.push_str(&from.to_string());
+                    }
+                }
+                if is_capture {
+                    san.push('x');
+                }
+                san.push_str(&to.to_string());
+            }
+        }
+
+        let mut board = self.clone();
+        board.apply_move(from, to, promotion.cloned());
+        if board.is_in_check(&board.players_turn).is_some() {
+            if board.get_all_legal_moves(&board.players_turn).is_empty() {
+                san.push('#');
+            } else {
+                san.push('+');
             }
         }
+        san
+    }
+
+    //? checks if the side to move has any legal moves left and if a draw rule applies
+    pub fn evaluate_status(&self) -> GameStatus {
+        if self.get_all_legal_moves(&self.players_turn).is_empty() {
+            if self.is_in_check(&self.players_turn).is_some() {
+                return GameStatus::Checkmate {
+                    winner: self.players_turn.opposite_color(),
+                };
+            }
+            return GameStatus::Stalemate;
+        }
+        if self.has_insufficient_material() {
+            return GameStatus::Draw {
+                reason: DrawReason::InsufficientMaterial,
+            };
+        }
+        if self.halfmove_clock >= 100 {
+            return GameStatus::Draw {
+                reason: DrawReason::FiftyMoveRule,
+            };
+        }
+        if self.count_repetitions() >= 3 {
+            return GameStatus::Draw {
+                reason: DrawReason::ThreefoldRepetition,
+            };
+        }
+        GameStatus::Ongoing
     }
 
-    pub fn is_check(&self) -> Option<Position> {
-        let possible_takes = self.get_all_possible_takes();
+    //? dead positions where no sequence of moves can end in a checkmate:
+    //? only kings, a single minor piece, or bishops that all stand on tiles of the same color
+    pub fn has_insufficient_material(&self) -> bool {
+        self.is_material_insufficient(None)
+    }
 
-        for possible in possible_takes {
-            if let GameObject::King(_) = self
-                .get_tile(&possible)
-                .borrow_mut()
-                .piece
-                .as_ref()
-                .unwrap()
-            {
-                return Some(possible);
+    //? the same rule for the pieces of one side only, a side that can not checkmate with them
+    //? does not win when the flag of the opponent falls
+    pub fn has_insufficient_material_for(&self, color: &Color) -> bool {
+        self.is_material_insufficient(Some(color))
+    }
+
+    fn is_material_insufficient(&self, color: Option<&Color>) -> bool {
+        let mut knights = 0;
+        let mut bishop_tiles = Vec::new();
+        for tile in self.board.iter().flatten() {
+            let tile = tile.borrow();
+            let piece = tile.piece.as_ref().filter(|piece| match color {
+                Some(color) => piece.get_color() == *color,
+                None => true,
+            });
+            match piece {
+                Some(GameObject::King(_)) | None => {}
+                Some(GameObject::Knight(_)) => knights += 1,
+                Some(GameObject::Bishop(_)) => bishop_tiles.push(tile.color.clone()),
+                Some(_) => return false,
             }
         }
-        None
+        match (knights, bishop_tiles.len()) {
+            (0, _) => bishop_tiles.windows(2).all(|pair| pair[0] == pair[1]),
+            (1, 0) => true,
+            _ => false,
+        }
     }
 
-    pub fn check_for_checkmate(&self, color: Color) -> bool {
-        let mut res = true;
-        let mut board_clone = self.clone();
-        let original_version = self.clone();
+    //? how often the current position occurred, including the current one
+    pub fn count_repetitions(&self) -> usize {
+        let current = self.position_key();
+        1 + self.history.iter().filter(|key| **key == current).count()
+    }
 
-        for (row, i) in self
-            .board
-            .iter()
-            .enumerate()
-            .collect::<Vec<(usize, &Vec<RefCell<Tile>>)>>()
-        {
-            if res == false {
-                return res;
+    pub fn to_fen(&self) -> String {
+        let en_passant = match &self.en_passant {
+            Some(pos) => pos.to_string(),
+            None => String::from("-"),
+        };
+        format!(
+            "{} {} {} {} {} {}",
+            self.placement(),
+            self.players_turn.fen_symbol(),
+            self.castling,
+            en_passant,
+            self.halfmove_clock,
+            self.fullmove_number
+        )
+    }
+
+    pub fn from_fen(fen: &str) -> Result<Board, String> {
+        let fields = fen.split_whitespace().collect::<Vec<&str>>();
+        if fields.len() != 6 {
+            return Err(format!("a FEN needs 6 fields, got {}", fields.len()));
+        }
+        let ranks = fields[0].split('/').collect::<Vec<&str>>();
+        if ranks.len() != 8 {
+            return Err(String::from("the piece placement needs 8 ranks"));
+        }
+
+        let mut rows: Matrix = Vec::with_capacity(8);
+        for (row, rank) in ranks.iter().rev().enumerate() {
+            let mut tiles = Vec::with_capacity(8);
+            for symbol in rank.chars() {
+                match symbol.to_digit(10) {
+                    Some(empty @ 1..=8) => {
+                        for _ in 0..empty {
+                            tiles.push(Tile::new(None, tile_color(row, tiles.len())));
+                        }
+                    }
+                    _ => {
+                        let piece = GameObject::from_fen_symbol(symbol)
+                            .ok_or(format!("invalid piece {} in FEN", symbol))?;
+                        tiles.push(Tile::new(Some(piece), tile_color(row, tiles.len())));
+                    }
+                }
             }
-            for (col, tile) in i
+            if tiles.len() != 8 {
+                return Err(format!("rank {} does not have 8 tiles", row + 1));
+            }
+            rows.push(tiles);
+        }
+
+        let players_turn = match fields[1] {
+            "w" => Color::White,
+            "b" => Color::Black,
+            side => return Err(format!("invalid side to move {}", side)),
+        };
+        let en_passant = match fields[3] {
+            "-" => None,
+            pos => Some(Position::from_str(pos)?),
+        };
+        let halfmove_clock = fields[4]
+            .parse::<u32>()
+            .map_err(|_| format!("invalid halfmove clock {}", fields[4]))?;
+        let fullmove_number = fields[5]
+            .parse::<u32>()
+            .map_err(|_| format!("invalid fullmove number {}", fields[5]))?;
+
+        let mut board = Board {
+            board: rows,
+            players_turn,
+            castling: CastlingRights::from_str(fields[2])?,
+            en_passant,
+            status: GameStatus::Ongoing,
+            halfmove_clock,
+            history: Vec::new(),
+            fullmove_number,
+        };
+        if let Some(pos) = &board.en_passant {
+            board.validate_en_passant(pos)?;
+        }
+        board.validate_castling()?;
+        for color in [Color::White, Color::Black] {
+            let kings = board
+                .board
                 .iter()
-                .enumerate()
-                .collect::<Vec<(usize, &RefCell<Tile>)>>()
+                .flatten()
+                .filter(|tile| {
+                    matches!(&tile.borrow().piece, Some(GameObject::King(king)) if king.get_color() == color)
+                })
+                .count();
+            if kings != 1 {
+                return Err(format!("{:?} needs exactly one king", color));
+            }
+        }
+        for row in [0, 7] {
+            if board.board[row]
+                .iter()
+                .any(|tile| matches!(tile.borrow().piece, Some(GameObject::Pawn(_))))
             {
-                if let Some(p) = tile.borrow_mut().piece.as_mut() {
-                    if color == p.get_color() {
-                        let starting_position = &Position::new_from_index(row, col);
-
-                        let moves = p.get_moves(starting_position, &self);
-                        for m in moves {
-                            if Self::is_still_check(&mut board_clone, starting_position, &m)
-                                == false
-                            {
-                                res = false;
-                            }
-                            board_clone = original_version.clone();
+                return Err(String::from(
+                    "pawns can not stand on the first or last rank",
+                ));
+            }
+        }
+        if board
+            .is_in_check(&board.players_turn.opposite_color())
+            .is_some()
+        {
+            return Err(String::from("the side that is not to move is in check"));
+        }
+        board.status = board.evaluate_status();
+        Ok(board)
+    }
+
+    //? identifies a position by its placement, side to move, castling rights and en passant square
+    pub fn position_key(&self) -> String {
+        //? the en passant square only matters if a pawn can actually take there
+        let en_passant = match &self.en_passant {
+            Some(pos) if self.can_take_en_passant(pos) => pos.to_string(),
+            _ => String::from("-"),
+        };
+        format!(
+            "{} {} {} {}",
+            self.placement(),
+            self.players_turn.fen_symbol(),
+            self.castling,
+            en_passant
+        )
+    }
+
+    //? every castling right needs its king and rook still on their starting squares
+    fn validate_castling(&self) -> Result<(), String> {
+        for (allowed, symbol, color, rook_file) in [
+            (self.castling.white_king_side, 'K', Color::White, 'h'),
+            (self.castling.white_queen_side, 'Q', Color::White, 'a'),
+            (self.castling.black_king_side, 'k', Color::Black, 'h'),
+            (self.castling.black_queen_side, 'q', Color::Black, 'a'),
+        ] {
+            let rank = match color {
+                Color::White => 1,
+                Color::Black => 8,
+            };
+            let king = Position::new('e', rank);
+            let rook = Position::new(rook_file, rank);
+            let king_home = matches!(
+                self.get_piece_of_color(&king, &color),
+                Some(GameObject::King(_))
+            );
+            let rook_home = matches!(
+                self.get_piece_of_color(&rook, &color),
+                Some(GameObject::Rook(_))
+            );
+            if allowed && !(king_home && rook_home) {
+                return Err(format!(
+                    "castling right {} needs the king on {} and a rook on {}",
+                    symbol, king, rook
+                ));
+            }
+        }
+        Ok(())
+    }
+
+    //? the square has to be the one a pawn of the side not to move just skipped with a double step,
+    //? so the pawn stands in front of it and the square and the one it came from are empty
+    fn validate_en_passant(&self, pos: &Position) -> Result<(), String> {
+        let (rank, forward) = match self.players_turn {
+            Color::White => (6, 1),
+            Color::Black => (3, -1),
+        };
+        let pushed_pawn = pos
+            .offset(0, -forward)
+            .and_then(|p| self.get_piece_of_color(&p, &self.players_turn.opposite_color()));
+        let is_valid = pos.rank == rank
+            && self.get_piece(pos).is_none()
+            && matches!(pos.offset(0, forward), Some(origin) if self.get_piece(&origin).is_none())
+            && matches!(pushed_pawn, Some(GameObject::Pawn(_)));
+        if !is_valid {
+            return Err(format!("{} is not a valid en passant square", pos));
+        }
+        Ok(())
+    }
+
+    fn can_take_en_passant(&self, pos: &Position) -> bool {
+        let pawn_rank = match self.players_turn {
+            Color::White => -1,
+            Color::Black => 1,
+        };
+        [-1, 1].into_iter().any(|file_delta| {
+            matches!(
+                pos.offset(file_delta, pawn_rank)
+                    .and_then(|p| self.get_piece_of_color(&p, &self.players_turn)),
+                Some(GameObject::Pawn(_))
+            )
+        })
+    }
+
+    //? the piece placement like in the first field of a FEN, starting from rank 8
+    pub fn placement(&self) -> String {
+        let mut res = String::new();
+        for (row, tiles) in self.board.iter().enumerate().rev() {
+            let mut empty = 0;
+            for tile in tiles {
+                match &tile.borrow().piece {
+                    Some(piece) => {
+                        if empty > 0 {
+                            res.push_str(&empty.to_string());
+                            empty = 0;
                         }
+                        res.push(piece.fen_symbol());
                     }
+                    None => empty += 1,
                 }
             }
+            if empty > 0 {
+                res.push_str(&empty.to_string());
+            }
+            if row > 0 {
+                res.push('/');
+            }
         }
-
         res
     }
 
-    fn is_still_check(board: &mut Board, from: &Position, to: &Position) -> bool {
-        let _ = board.move_piece(from, to);
-        board.is_check().is_some()
+    //? the moves of get_moves that do not leave the own king attacked
+    pub fn get_legal_moves(&self, pos: &Position) -> Vec<Position> {
+        let color = match self.get_piece(pos) {
+            Some(piece) => piece.get_color(),
+            None => return Vec::with_capacity(0),
+        };
+        self.get_moves_of_piece(pos)
+            .into_iter()
+            .filter(|to| {
+                let mut board = self.clone();
+                board.apply_move(pos, to, None);
+                match board.find_king(&color) {
+                    Some(king) => !board.is_square_attacked(&king, &color.opposite_color()),
+                    None => true,
+                }
+            })
+            .collect()
     }
-    fn get_all_possible_takes(&self) -> HashSet<Position> {
-        let mut possible_takes = HashSet::new();
 
-        for (row, i) in self
-            .board
-            .iter()
-            .enumerate()
-            .collect::<Vec<(usize, &Vec<RefCell<Tile>>)>>()
-        {
-            for (col, j) in i
-                .iter()
-                .enumerate()
-                .collect::<Vec<(usize, &RefCell<Tile>)>>()
-            {
-                if let Some(val) = j.borrow_mut().piece.as_mut() {
-                    for pos in val.get_moves(&Position::new_from_index(row, col), self) {
-                        if self.is_piece_in_position(&pos).is_some() {
-                            possible_takes.insert(pos);
-                        }
+    fn find_king(&self, color: &Color) -> Option<Position> {
+        for (row, tiles) in self.board.iter().enumerate() {
+            for (col, tile) in tiles.iter().enumerate() {
+                if let Some(GameObject::King(king)) = &tile.borrow().piece {
+                    if king.get_color() == *color {
+                        return Some(Position::new_from_index(row, col));
+                    }
+                }
+            }
+        }
+        None
+    }
+
+    fn relocate_piece(&mut self, from: &Position, to: &Position) -> Option<GameObject> {
+        let piece = self.get_tile(from).borrow_mut().piece.take()?;
+        //todo add_piece probably should not return anything
+        self.get_tile(to).borrow_mut().add_piece(piece)
+    }
+
+    fn next_turn(&mut self) {
+        match self.players_turn {
+            Color::Black => {
+                self.players_turn = Color::White;
+            }
+            Color::White => {
+                self.players_turn = Color::Black;
+            }
+        }
+    }
+
+    //? the position of the king of the given color if it is attacked
+    pub fn is_in_check(&self, color: &Color) -> Option<Position> {
+        self.find_king(color)
+            .filter(|king| self.is_square_attacked(king, &color.opposite_color()))
+    }
+
+    pub fn check_for_checkmate(&self, color: Color) -> bool {
+        self.is_in_check(&color).is_some() && self.get_all_legal_moves(&color).is_empty()
+    }
+
+    pub fn get_all_legal_moves(&self, color: &Color) -> Vec<(Position, Position)> {
+        let mut moves = Vec::new();
+        for (row, tiles) in self.board.iter().enumerate() {
+            for (col, tile) in tiles.iter().enumerate() {
+                let is_own_piece = match &tile.borrow().piece {
+                    Some(piece) => piece.get_color() == *color,
+                    None => false,
+                };
+                if is_own_piece {
+                    let from = Position::new_from_index(row, col);
+                    for to in self.get_legal_moves(&from) {
+                        moves.push((from.clone(), to));
                     }
                 }
             }
         }
-        possible_takes
+        moves
+    }
+
+    //? every tile a piece of the given color could take on, no matter what stands there
+    pub fn get_attacked_squares(&self, by: &Color) -> HashSet<Position> {
+        let mut attacked = HashSet::new();
+        for row in 0..8 {
+            for col in 0..8 {
+                let pos = Position::new_from_index(row, col);
+                if self.is_square_attacked(&pos, by) {
+                    attacked.insert(pos);
+                }
+            }
+        }
+        attacked
     }
 
     fn is_piece_in_position(&self, pos: &Position) -> Option<Color> {
@@ -271,21 +1211,108 @@ impl Board {
     }
 
     pub fn show_moves_of_tile(&self, pos: &Position) -> Vec<Position> {
-        let (rank, file) = convert_position_to_index(pos);
+        self.get_legal_moves(pos)
+    }
 
-        let mut tile = self
-            .board
-            .get(rank)
-            .unwrap()
-            .get(file)
-            .unwrap()
-            .borrow_mut();
-        match tile.piece.borrow_mut() {
-            Some(piece) => piece.get_moves(pos, self),
+    //? the piece is cloned so get_moves can inspect every tile, including the one it stands on
+    fn get_moves_of_piece(&self, pos: &Position) -> Vec<Position> {
+        match self.get_piece(pos) {
+            Some(mut piece) => piece.get_moves(pos, self),
             None => Vec::with_capacity(0),
         }
     }
 
+    pub fn is_promotion(&self, from: &Position, to: &Position) -> bool {
+        matches!(self.get_piece(from), Some(GameObject::Pawn(_))) && (to.rank == 1 || to.rank == 8)
+    }
+
+    //? only the side to move can take en passant, directly after the double step
+    fn is_en_passant_target(&self, pos: &Position, color: &Color) -> bool {
+        *color == self.players_turn && self.en_passant.as_ref() == Some(pos)
+    }
+
+    pub fn get_piece(&self, pos: &Position) -> Option<GameObject> {
+        self.get_tile(pos).borrow().piece.clone()
+    }
+
+    fn get_piece_of_color(&self, pos: &Position, color: &Color) -> Option<GameObject> {
+        self.get_piece(pos)
+            .filter(|piece| piece.get_color() == *color)
+    }
+
+    //? looks outwards from the square for anything of the given color that could take on it
+    pub fn is_square_attacked(&self, pos: &Position, by: &Color) -> bool {
+        let pawn_direction = match by {
+            Color::White => -1,
+            Color::Black => 1,
+        };
+        for file_delta in [-1, 1] {
+            if let Some(p) = pos.offset(file_delta, pawn_direction) {
+                if let Some(GameObject::Pawn(_)) = self.get_piece_of_color(&p, by) {
+                    return true;
+                }
+            }
+        }
+        for (file_delta, rank_delta) in KNIGHT_OFFSETS {
+            if let Some(p) = pos.offset(file_delta, rank_delta) {
+                if let Some(GameObject::Knight(_)) = self.get_piece_of_color(&p, by) {
+                    return true;
+                }
+            }
+        }
+        for (file_delta, rank_delta) in ORTHOGONAL_DIRECTIONS.into_iter().chain(DIAGONAL_DIRECTIONS)
+        {
+            if let Some(p) = pos.offset(file_delta, rank_delta) {
+                if let Some(GameObject::King(_)) = self.get_piece_of_color(&p, by) {
+                    return true;
+                }
+            }
+        }
+        for (file_delta, rank_delta) in ORTHOGONAL_DIRECTIONS {
+            match self.first_piece_in_direction(pos, file_delta, rank_delta) {
+                Some(GameObject::Rook(piece)) if piece.get_color() == *by => return true,
+                Some(GameObject::Queen(piece)) if piece.get_color() == *by => return true,
+                _ => {}
+            }
+        }
+        for (file_delta, rank_delta) in DIAGONAL_DIRECTIONS {
+            match self.first_piece_in_direction(pos, file_delta, rank_delta) {
+                Some(GameObject::Bishop(piece)) if piece.get_color() == *by => return true,
+                Some(GameObject::Queen(piece)) if piece.get_color() == *by => return true,
+                _ => {}
+            }
+        }
+        false
+    }
+
+    fn first_piece_in_direction(
+        &self,
+        pos: &Position,
+        file_delta: i8,
+        rank_delta: i8,
+    ) -> Option<GameObject> {
+        let mut current = pos.offset(file_delta, rank_delta);
+        while let Some(p) = current {
+            if let Some(piece) = self.get_piece(&p) {
+                return Some(piece);
+            }
+            current = p.offset(file_delta, rank_delta);
+        }
+        None
+    }
+
+    //? castling needs empty files between king and rook and no attacked square on the kings way
+    fn can_castle(&self, color: &Color, rank: u8, empty: &[char], safe: &[char]) -> bool {
+        let enemy = color.opposite_color();
+        empty
+            .iter()
+            .all(|file| self.get_piece(&Position::new(*file, rank)).is_none())
+            && safe
+                .iter()
+                .all(|file| !self.is_square_attacked(&Position::new(*file, rank), &enemy))
+    }
+
+    #[allow(dead_code)]
     pub fn print_with_marked(&self, pos: &Position) {
         println!("{}-{}", pos.file, pos.rank);
 
@@ -311,6 +1338,10 @@ impl Board {
         println!("{:?} to Move!", self.players_turn);
     }
 
+    pub fn get_tile_color(&self, pos: &Position) -> Color {
+        self.get_tile(pos).borrow().color.clone()
+    }
+
     fn get_tile(&self, pos: &Position) -> &RefCell<Tile> {
         let (rank, file) = convert_position_to_index(pos);
 
@@ -318,6 +1349,24 @@ impl Board {
     }
 }
 
+//? drops check markers and annotations so "Nf3+!" matches "Nf3", castling may be written with zeros
+fn normalize_san(san: &str) -> String {
+    let san = san.trim().trim_end_matches(['+', '#', '!', '?']);
+    if san.chars().all(|c| c == '0' || c == '-') {
+        return san.replace('0', "O");
+    }
+    san.replace('=', "")
+}
+
+//? a1 is a dark tile, from there the colors alternate
+fn tile_color(row: usize, col: usize) -> Color {
+    if (row + col) % 2 == 1 {
+        Color::White
+    } else {
+        Color::Black
+    }
+}
+
 fn convert_position_to_index(pos: &Position) -> (usize, usize) {
     //todo this function can panic
 
@@ -376,44 +1425,43 @@ impl Display for Board {
     }
 }
 
-pub fn create_game() -> Arc<Mutex<Board>> {
+pub fn create_game() -> Db {
     let mut rows = Vec::with_capacity(8);
     let mut black_start: Vec<RefCell<Tile>> = Vec::with_capacity(8);
     let mut white_start: Vec<RefCell<Tile>> = Vec::with_capacity(8);
 
     for i in 0..8 {
+        //? a1 is a dark tile, so the first rank starts with black
         if i % 2 == 0 {
-            black_start.push(Tile::new(None, Color::Black));
-            white_start.push(Tile::new(None, Color::White));
-        } else {
             black_start.push(Tile::new(None, Color::White));
             white_start.push(Tile::new(None, Color::Black));
+        } else {
+            black_start.push(Tile::new(None, Color::Black));
+            white_start.push(Tile::new(None, Color::White));
         }
     }
     let black_pawns = white_start
         .clone()
         .into_iter()
-        .map(|tile| {
+        .inspect(|tile| {
             tile.borrow_mut()
                 .add_piece(GameObject::Pawn(Pawn::new(Color::Black)));
-            tile
         })
         .collect();
     let white_pawns: Vec<RefCell<Tile>> = black_start
         .clone()
         .into_iter()
-        .map(|tile| {
+        .inspect(|tile| {
             tile.borrow_mut()
                 .add_piece(GameObject::Pawn(Pawn::new(Color::White)));
-            tile
         })
         .collect();
     let mut pieces = [
         GameObject::Rook(Rook::new(Color::White)),
         GameObject::Knight(Knight::new(Color::White)),
         GameObject::Bishop(Bishop::new(Color::White)),
-        GameObject::King(King::new(Color::White)),
         GameObject::Queen(Queen::new(Color::White)),
+        GameObject::King(King::new(Color::White)),
         GameObject::Bishop(Bishop::new(Color::White)),
         GameObject::Knight(Knight::new(Color::White)),
         GameObject::Rook(Rook::new(Color::White)),
@@ -452,10 +1500,16 @@ pub fn create_game() -> Arc<Mutex<Board>> {
     Arc::new(Mutex::new(Board {
         board: rows,
         players_turn: Color::White,
+        castling: CastlingRights::default(),
+        en_passant: None,
+        status: GameStatus::Ongoing,
+        halfmove_clock: 0,
+        history: Vec::new(),
+        fullmove_number: 1,
     }))
 }
 
-trait Piece {
+pub(crate) trait Piece {
     fn symbol(&self) -> &'static str;
     fn get_moves(&mut self, pos: &Position, db: &Board) -> Vec<Position>;
     fn get_color(&self) -> Color;
@@ -487,6 +1541,53 @@ impl FromRow<'_, SqliteRow> for GameObject {
     }
 }
 
+impl GameObject {
+    pub fn from_fen_symbol(symbol: char) -> Option<GameObject> {
+        let color = if symbol.is_ascii_uppercase() {
+            Color::White
+        } else {
+            Color::Black
+        };
+        Some(match symbol.to_ascii_lowercase() {
+            'p' => GameObject::Pawn(Pawn::new(color)),
+            'r' => GameObject::Rook(Rook::new(color)),
+            'n' => GameObject::Knight(Knight::new(color)),
+            'b' => GameObject::Bishop(Bishop::new(color)),
+            'q' => GameObject::Queen(Queen::new(color)),
+            'k' => GameObject::King(King::new(color)),
+            _ => return None,
+        })
+    }
+
+    //? the representation used in the piece_names table
+    pub fn name(&self) -> &'static str {
+        match self {
+            GameObject::Pawn(_) => "PAWN",
+            GameObject::Rook(_) => "ROOK",
+            GameObject::Knight(_) => "KNIGHT",
+            GameObject::Bishop(_) => "BISHOP",
+            GameObject::Queen(_) => "QUEEN",
+            GameObject::King(_) => "KING",
+        }
+    }
+
+    //? the letter used in FEN, uppercase for white and lowercase for black
+    pub fn fen_symbol(&self) -> char {
+        let symbol = match self {
+            GameObject::Pawn(_) => 'p',
+            GameObject::Rook(_) => 'r',
+            GameObject::Knight(_) => 'n',
+            GameObject::Bishop(_) => 'b',
+            GameObject::Queen(_) => 'q',
+            GameObject::King(_) => 'k',
+        };
+        match self.get_color() {
+            Color::White => symbol.to_ascii_uppercase(),
+            Color::Black => symbol,
+        }
+    }
+}
+
 impl Piece for GameObject {
     fn symbol(&self) -> &'static str {
         match self {
@@ -546,7 +1647,33 @@ impl FromRow<'_, SqliteRow> for Color {
     }
 }
 
+impl FromStr for Color {
+    type Err = String;
+    fn from_str(s: &str) -> Result<Self, Self::Err> {
+        match s {
+            "WHITE" => Ok(Color::White),
+            "BLACK" => Ok(Color::Black),
+            var => Err(format!("color was not allowed {}", var)),
+        }
+    }
+}
+
 impl Color {
+    pub fn fen_symbol(&self) -> char {
+        match self {
+            Self::White => 'w',
+            Self::Black => 'b',
+        }
+    }
+
+    //? the representation used in the piece_colors table
+    pub fn as_str(&self) -> &'static str {
+        match self {
+            Self::White => "WHITE",
+            Self::Black => "BLACK",
+        }
+    }
+
     pub fn opposite_color(&self) -> Self {
         match self {
             Self::Black => Color::White,
@@ -601,3 +1728,310 @@ impl Tile {
         old
     }
 }
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn pos(square: &str) -> Position {
+        Position::from_str(square).unwrap()
+    }
+
+    fn starting_board() -> Board {
+        create_game().lock().unwrap().clone()
+    }
+
+    //? moves are given by their squares like "e2e4"
+    fn play(board: &mut Board, moves: &[&str]) {
+        for squares in moves {
+            let (from, to) = squares.split_at(2);
+            board.move_piece(&pos(from), &pos(to), None).unwrap();
+        }
+    }
+
+    #[test]
+    fn pinned_pieces_can_not_leave_the_line() {
+        let mut board = starting_board();
+        play(&mut board, &["d2d4", "e7e6", "b1c3", "f8b4"]);
+        assert!(board.get_legal_moves(&pos("c3")).is_empty());
+        assert_eq!(
+            board.move_piece(&pos("c3"), &pos("e4"), None).unwrap_err(),
+            "illegal move, your king would be in check"
+        );
+    }
+
+    #[test]
+    fn only_moves_that_end_the_check_are_legal() {
+        let mut board = starting_board();
+        play(&mut board, &["e2e4", "d7d5", "e1e2", "c8g4"]);
+        let moves = board.get_legal_moves(&pos("e2"));
+        assert!(!moves.contains(&pos("f3")));
+        assert!(moves.contains(&pos("e1")));
+        assert!(moves.contains(&pos("d3")));
+        assert!(moves.contains(&pos("e3")));
+        assert!(board.get_legal_moves(&pos("e4")).is_empty());
+        assert_eq!(board.get_legal_moves(&pos("g1")), vec![pos("f3")]);
+        assert!(board.get_legal_moves(&pos("f2")).contains(&pos("f3")));
+    }
+
+    #[test]
+    fn castling_moves_the_rook_and_ends_the_rights() {
+        let mut board = starting_board();
+        play(
+            &mut board,
+            &["e2e4", "e7e5", "g1f3", "b8c6", "f1c4", "f8c5"],
+        );
+        assert!(board.show_moves_of_tile(&pos("e1")).contains(&pos("g1")));
+        let moved = board.move_piece(&pos("e1"), &pos("g1"), None).unwrap();
+        assert_eq!(moved.castling_rook, Some((pos("h1"), pos("f1"))));
+        assert!(matches!(
+            board.get_piece(&pos("f1")),
+            Some(GameObject::Rook(_))
+        ));
+        assert!(board.get_piece(&pos("h1")).is_none());
+        assert_eq!(board.castling.to_string(), "kq");
+    }
+
+    #[test]
+    fn castling_does_not_pass_through_or_start_in_check() {
+        let mut board = starting_board();
+        play(
+            &mut board,
+            &["g2g3", "b7b6", "f1g2", "c8a6", "g1f3", "e7e6"],
+        );
+        assert!(board.show_moves_of_tile(&pos("e1")).contains(&pos("g1")));
+        //? the bishop on a6 now covers f1
+        play(&mut board, &["e2e4", "b8c6"]);
+        assert!(!board.show_moves_of_tile(&pos("e1")).contains(&pos("g1")));
+
+        let mut board = starting_board();
+        play(
+            &mut board,
+            &[
+                "g2g3", "e7e6", "f1g2", "b8c6", "g1f3", "g8f6", "d2d4", "f8b4",
+            ],
+        );
+        assert!(!board.show_moves_of_tile(&pos("e1")).contains(&pos("g1")));
+    }
+
+    #[test]
+    fn checkmate_and_stalemate_end_the_game() {
+        let mut board = starting_board();
+        play(
+            &mut board,
+            &["e2e4", "e7e5", "f1c4", "b8c6", "d1h5", "g8f6", "h5f7"],
+        );
+        assert_eq!(
+            board.status,
+            GameStatus::Checkmate {
+                winner: Color::White
+            }
+        );
+        assert!(board.move_piece(&pos("e8"), &pos("f7"), None).is_err());
+
+        //? the shortest known stalemate, found by Sam Loyd
+        let mut board = starting_board();
+        play(
+            &mut board,
+            &[
+                "e2e3", "a7a5", "d1h5", "a8a6", "h5a5", "h7h5", "h2h4", "a6h6", "a5c7", "f7f6",
+                "c7d7", "e8f7", "d7b7", "d8d3", "b7b8", "d3h7", "b8c8", "f7g6", "c8e6",
+            ],
+        );
+        assert_eq!(board.status, GameStatus::Stalemate);
+    }
+
+    #[test]
+    fn fen_round_trips() {
+        for fen in [
+            "rnbqkbnr/pppppppp/8/8/8/8/PPPPPPPP/RNBQKBNR w KQkq - 0 1",
+            "r3k2r/pppq1ppp/2np1n2/2b1p1B1/2B1P1b1/2NP1N2/PPPQ1PPP/R3K2R w KQkq - 4 8",
+            "rnbqkbnr/ppp1pppp/8/3pP3/8/8/PPPP1PPP/RNBQKBNR w KQkq d6 0 3",
+            "8/5k2/8/8/8/8/2K5/8 b - - 37 61",
+        ] {
+            assert_eq!(Board::from_fen(fen).unwrap().to_fen(), fen);
+        }
+    }
+
+    #[test]
+    fn fen_follows_the_moves() {
+        let mut board = starting_board();
+        assert_eq!(
+            board.to_fen(),
+            "rnbqkbnr/pppppppp/8/8/8/8/PPPPPPPP/RNBQKBNR w KQkq - 0 1"
+        );
+        play(&mut board, &["e2e4"]);
+        assert_eq!(
+            board.to_fen(),
+            "rnbqkbnr/pppppppp/8/8/4P3/8/PPPP1PPP/RNBQKBNR b KQkq e3 0 1"
+        );
+        play(&mut board, &["g8f6", "e1e2"]);
+        assert_eq!(
+            board.to_fen(),
+            "rnbqkb1r/pppppppp/5n2/8/4P3/8/PPPPKPPP/RNBQ1BNR b kq - 2 2"
+        );
+    }
+
+    #[test]
+    fn castling_rights_need_the_king_and_rook_at_home() {
+        for fen in [
+            "4k3/8/8/8/8/8/8/4K3 w K - 0 1",
+            "4k3/8/8/8/8/8/8/R3K3 w K - 0 1",
+            "4k3/8/8/8/8/8/8/3K3R w K - 0 1",
+            "4k3/8/8/8/8/8/8/4K2r w K - 0 1",
+            "4k2r/8/8/8/8/8/8/4K3 w q - 0 1",
+            "r4k2/8/8/8/8/8/8/4K3 w q - 0 1",
+        ] {
+            assert!(Board::from_fen(fen).is_err(), "{fen}");
+        }
+        let board = Board::from_fen("r3k3/8/8/8/8/8/8/4K2R w Kq - 0 1").unwrap();
+        assert_eq!(board.castling.to_string(), "Kq");
+    }
+
+    #[test]
+    fn from_fen_rejects_impossible_positions() {
+        for fen in [
+            //? a missing field and a missing rank
+            "rnbqkbnr/pppppppp/8/8/8/8/PPPPPPPP/RNBQKBNR w KQkq - 0",
+            "rnbqkbnr/pppppppp/8/8/8/PPPPPPPP/RNBQKBNR w KQkq - 0 1",
+            //? a rank with nine tiles and an unknown piece
+            "rnbqkbnr/pppppppp/9/8/8/8/PPPPPPPP/RNBQKBNR w KQkq - 0 1",
+            "rnbqkbnr/pppppppp/8/8/8/8/PPPPPPPP/RNBQKBNX w KQkq - 0 1",
+            //? two white kings and no black king
+            "4k3/8/8/8/8/8/8/3KK3 w - - 0 1",
+            "8/8/8/8/8/8/8/4K3 w - - 0 1",
+            //? pawns on the first or last rank
+            "P3k3/8/8/8/8/8/8/4K3 w - - 0 1",
+            "4k3/8/8/8/8/8/8/p3K3 w - - 0 1",
+            //? black is in check while white is to move
+            "4k3/8/8/8/8/8/4Q3/4K3 w - - 0 1",
+            "4k3/8/8/8/8/8/8/4K3 x - - 0 1",
+        ] {
+            assert!(Board::from_fen(fen).is_err(), "{fen}");
+        }
+    }
+
+    #[test]
+    fn san_names_the_moving_piece_only_as_precisely_as_needed() {
+        let board = Board::from_fen("4k3/8/8/8/8/5N2/8/1N2K3 w - - 0 1").unwrap();
+        assert_eq!(board.to_san(&pos("b1"), &pos("d2"), None), "Nbd2");
+        assert_eq!(board.to_san(&pos("f3"), &pos("h4"), None), "Nh4");
+        let board = Board::from_fen("4k3/8/8/R7/8/8/8/R3K3 w - - 0 1").unwrap();
+        assert_eq!(board.to_san(&pos("a1"), &pos("a3"), None), "R1a3");
+        let board = Board::from_fen("4k3/8/8/8/8/Q7/8/Q1Q1K3 w - - 0 1").unwrap();
+        assert_eq!(board.to_san(&pos("a1"), &pos("b2"), None), "Qa1b2");
+    }
+
+    #[test]
+    fn san_marks_captures_promotions_and_checks() {
+        let board = Board::from_fen("4k3/1P6/8/3p4/4P3/8/8/4K3 w - - 0 1").unwrap();
+        assert_eq!(board.to_san(&pos("e4"), &pos("d5"), None), "exd5");
+        assert_eq!(
+            board.to_san(&pos("b7"), &pos("b8"), Some(&Promotion::Queen)),
+            "b8=Q+"
+        );
+        assert_eq!(
+            board.to_san(&pos("b7"), &pos("b8"), Some(&Promotion::Knight)),
+            "b8=N"
+        );
+    }
+
+    #[test]
+    fn parse_san_finds_the_move_or_explains_why_not() {
+        let board = Board::from_fen("4k3/8/8/8/8/5N2/8/1N2K3 w - - 0 1").unwrap();
+        assert_eq!(board.parse_san("Nbd2"), Ok((pos("b1"), pos("d2"), None)));
+        assert_eq!(board.parse_san("N1d2"), Ok((pos("b1"), pos("d2"), None)));
+        assert_eq!(board.parse_san("Nfxd2+"), Ok((pos("f3"), pos("d2"), None)));
+        assert_eq!(board.parse_san("Nd2").unwrap_err(), "Nd2 is ambiguous");
+        assert!(board.parse_san("Nd3").is_err());
+
+        let board = Board::from_fen("4k3/1P6/8/8/8/8/8/R3K3 w Q - 0 1").unwrap();
+        assert_eq!(
+            board.parse_san("b8=Q+"),
+            Ok((pos("b7"), pos("b8"), Some(Promotion::Queen)))
+        );
+        assert_eq!(
+            board.parse_san("b8n"),
+            Ok((pos("b7"), pos("b8"), Some(Promotion::Knight)))
+        );
+        assert!(board.parse_san("b8").is_err());
+        assert_eq!(board.parse_san("O-O-O"), Ok((pos("e1"), pos("c1"), None)));
+        assert_eq!(board.parse_san("0-0-0"), Ok((pos("e1"), pos("c1"), None)));
+        assert!(board.parse_san("O-O").is_err());
+    }
+
+    #[test]
+    fn positions_are_parsed_without_panicking() {
+        assert_eq!(Position::from_str("e4"), Ok(Position::new('e', 4)));
+        assert_eq!(Position::from_str("H8"), Ok(Position::new('h', 8)));
+        for input in ["é", "e", "e44", "i1", "a0", "", "ée"] {
+            assert!(Position::from_str(input).is_err(), "{input}");
+        }
+    }
+
+    #[test]
+    fn from_fen_accepts_the_square_behind_a_double_step() {
+        let board = Board::from_fen("rnbqkbnr/ppp1pppp/8/3pP3/8/8/PPPP1PPP/RNBQKBNR w KQkq d6 0 3")
+            .unwrap();
+        assert_eq!(board.en_passant, Some(Position::new('d', 6)));
+        let board =
+            Board::from_fen("rnbqkbnr/pppppppp/8/8/4P3/8/PPPP1PPP/RNBQKBNR b KQkq e3 0 1").unwrap();
+        assert_eq!(board.en_passant, Some(Position::new('e', 3)));
+    }
+
+    #[test]
+    fn from_fen_rejects_invalid_en_passant_squares() {
+        for fen in [
+            //? wrong rank for the side to move
+            "8/8/8/8/8/3Pk3/8/K7 w - e4 0 1",
+            "rnbqkbnr/pppppppp/8/8/4P3/8/PPPP1PPP/RNBQKBNR w KQkq e3 0 1",
+            //? no pawn in front of the square
+            "rnbqkbnr/ppp1pppp/8/4P3/8/8/PPPP1PPP/RNBQKBNR w KQkq d6 0 3",
+            //? the pawn in front belongs to the side to move
+            "rnbqkbnr/ppp1pppp/8/3P4/8/8/PPP1PPPP/RNBQKBNR w KQkq d6 0 3",
+            //? the square or the one the pawn came from is taken
+            "rnbqkbnr/ppp1pppp/3n4/3pP3/8/8/PPPP1PPP/RNBQKBNR w KQkq d6 0 3",
+            "rnbqkbnr/pppppppp/8/3pP3/8/8/PPPP1PPP/RNBQKBNR w KQkq d6 0 3",
+        ] {
+            assert!(Board::from_fen(fen).is_err(), "{fen}");
+        }
+    }
+
+    #[test]
+    fn en_passant_only_takes_a_pawn_of_the_opponent() {
+        let mut board = Board::from_fen("8/8/8/8/8/3Pk3/8/K7 w - - 0 1").unwrap();
+        board.en_passant = Some(Position::new('e', 4));
+        let moved = board
+            .move_piece(&Position::new('d', 3), &Position::new('e', 4), None)
+            .unwrap();
+        assert_eq!(moved.en_passant_capture, None);
+        assert!(matches!(
+            board.get_piece(&Position::new('e', 3)),
+            Some(GameObject::King(_))
+        ));
+    }
+
+    #[test]
+    fn a_lone_king_has_no_mating_material() {
+        let board = Board::from_fen("8/8/8/4k3/8/8/Q7/4K3 w - - 0 1").unwrap();
+        assert!(board.has_insufficient_material_for(&Color::Black));
+        assert!(!board.has_insufficient_material_for(&Color::White));
+        assert!(!board.has_insufficient_material());
+        let board = Board::from_fen("8/8/8/4k3/8/8/3N4/4K1n1 w - - 0 1").unwrap();
+        assert!(board.has_insufficient_material_for(&Color::White));
+        assert!(board.has_insufficient_material_for(&Color::Black));
+    }
+
+    #[test]
+    fn en_passant_takes_the_passed_pawn() {
+        let mut board =
+            Board::from_fen("rnbqkbnr/ppp1pppp/8/3pP3/8/8/PPPP1PPP/RNBQKBNR w KQkq d6 0 3")
+                .unwrap();
+        let moved = board
+            .move_piece(&Position::new('e', 5), &Position::new('d', 6), None)
+            .unwrap();
+        assert_eq!(moved.san, "exd6");
+        assert_eq!(moved.en_passant_capture, Some(Position::new('d', 5)));
+        assert!(board.get_piece(&Position::new('d', 5)).is_none());
+    }
+}
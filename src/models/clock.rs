@@ -0,0 +1,242 @@
+use super::{ClockKind, Color, TimeControl};
+use serde::Serialize;
+
+const MILLIS_PER_DAY: i64 = 24 * 60 * 60 * 1000;
+
+//? milliseconds since the unix epoch, all clock times are measured in it
+pub fn now() -> i64 {
+    chrono::Utc::now().timestamp_millis()
+}
+
+//? the remaining time of each side in milliseconds at the start of the current turn,
+//? the side to move is charged from turn_started_at on. The clock only starts with the first
+//? move of black, so the first move of each side is free.
+#[derive(Debug, Clone, PartialEq, Serialize)]
+pub struct Clock {
+    pub white_ms: i64,
+    pub black_ms: i64,
+    pub turn_started_at: Option<i64>,
+}
+
+impl Clock {
+    pub fn new(time_control: &TimeControl) -> Self {
+        let start = match time_control.kind {
+            ClockKind::Correspondence => time_control.days_per_move as i64 * MILLIS_PER_DAY,
+            _ => time_control.initial as i64 * 1000,
+        };
+        Clock {
+            white_ms: start,
+            black_ms: start,
+            turn_started_at: None,
+        }
+    }
+
+    fn remaining_mut(&mut self, color: &Color) -> &mut i64 {
+        match color {
+            Color::White => &mut self.white_ms,
+            Color::Black => &mut self.black_ms,
+        }
+    }
+
+    //? the time the given side has left at the given moment, only the side to move is running
+    pub fn remaining_at(
+        &self,
+        color: &Color,
+        side_to_move: &Color,
+        time_control: &TimeControl,
+        now: i64,
+    ) -> i64 {
+        let remaining = match color {
+            Color::White => self.white_ms,
+            Color::Black => self.black_ms,
+        };
+        match self.turn_started_at {
+            Some(started) if color == side_to_move => {
+                remaining - charged_time(time_control, now - started)
+            }
+            _ => remaining,
+        }
+    }
+
+    pub fn is_flagged(&self, side_to_move: &Color, time_control: &TimeControl, now: i64) -> bool {
+        self.remaining_at(side_to_move, side_to_move, time_control, now) <= 0
+    }
+
+    //? charges the side to move up to now and freezes both times, e.g. once the game is over
+    pub fn stop(&mut self, side_to_move: &Color, time_control: &TimeControl, now: i64) {
+        let left = self
+            .remaining_at(side_to_move, side_to_move, time_control, now)
+            .max(0);
+        *self.remaining_mut(side_to_move) = left;
+        self.turn_started_at = None;
+    }
+
+    //? charges the side to move up to now and starts a new turn, used when the moves change
+    //? without anyone moving, e.g. with a takeback
+    pub fn restart(&mut self, side_to_move: &Color, time_control: &TimeControl, now: i64) {
+        let running = self.turn_started_at.is_some();
+        self.stop(side_to_move, time_control, now);
+        if running {
+            self.turn_started_at = Some(now);
+        }
+    }
+
+    //? the clock as it was after an earlier move, e.g. with a takeback. The turn that follows
+    //? starts again now if the clock was running then.
+    pub fn resume(&self, now: i64) -> Clock {
+        Clock {
+            turn_started_at: self.turn_started_at.map(|_| now),
+            ..self.clone()
+        }
+    }
+
+    //? charges the mover for the turn and starts the clock of the opponent,
+    //? fails if the flag of the mover fell before the move arrived
+    pub fn punch(
+        &mut self,
+        mover: &Color,
+        time_control: &TimeControl,
+        now: i64,
+    ) -> Result<(), String> {
+        if self.is_flagged(mover, time_control, now) {
+            return Err(String::from("your time is up"));
+        }
+        let left = self.remaining_at(mover, mover, time_control, now);
+        *self.remaining_mut(mover) = match time_control.kind {
+            ClockKind::Fischer => left + time_control.increment as i64 * 1000,
+            ClockKind::Bronstein => left,
+            ClockKind::Correspondence => time_control.days_per_move as i64 * MILLIS_PER_DAY,
+        };
+        if self.turn_started_at.is_some() || *mover == Color::Black {
+            self.turn_started_at = Some(now);
+        }
+        Ok(())
+    }
+}
+
+//? with a bronstein delay the first seconds of a move are not charged
+fn charged_time(time_control: &TimeControl, elapsed: i64) -> i64 {
+    match time_control.kind {
+        ClockKind::Bronstein => (elapsed - time_control.increment as i64 * 1000).max(0),
+        _ => elapsed,
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn time_control(kind: ClockKind, initial: u32, increment: u32) -> TimeControl {
+        TimeControl {
+            kind,
+            initial,
+            increment,
+            days_per_move: 0,
+        }
+    }
+
+    //? both sides have played their free first move, white is to move since the given moment
+    fn running(time_control: &TimeControl, started: i64) -> Clock {
+        let mut clock = Clock::new(time_control);
+        clock.punch(&Color::White, time_control, 0).unwrap();
+        clock.punch(&Color::Black, time_control, started).unwrap();
+        clock
+    }
+
+    #[test]
+    fn the_clock_starts_with_the_first_move_of_black() {
+        let blitz = time_control(ClockKind::Fischer, 180, 0);
+        let mut clock = Clock::new(&blitz);
+        clock.punch(&Color::White, &blitz, 5_000).unwrap();
+        assert_eq!(clock.turn_started_at, None);
+        assert_eq!(
+            clock.remaining_at(&Color::Black, &Color::Black, &blitz, 60_000),
+            180_000
+        );
+        clock.punch(&Color::Black, &blitz, 70_000).unwrap();
+        assert_eq!(clock.turn_started_at, Some(70_000));
+        assert_eq!(clock.black_ms, 180_000);
+    }
+
+    #[test]
+    fn fischer_adds_the_increment_after_the_move() {
+        let rapid = time_control(ClockKind::Fischer, 600, 5);
+        let mut clock = running(&rapid, 0);
+        //? the free first moves got their increment too
+        assert_eq!(
+            clock.remaining_at(&Color::White, &Color::White, &rapid, 12_000),
+            593_000
+        );
+        //? only the side to move is running
+        assert_eq!(
+            clock.remaining_at(&Color::Black, &Color::White, &rapid, 12_000),
+            605_000
+        );
+        clock.punch(&Color::White, &rapid, 12_000).unwrap();
+        assert_eq!(clock.white_ms, 598_000);
+        assert_eq!(clock.turn_started_at, Some(12_000));
+    }
+
+    #[test]
+    fn bronstein_gives_back_the_delay_but_never_more_than_was_used() {
+        let blitz = time_control(ClockKind::Bronstein, 300, 3);
+        let mut clock = running(&blitz, 0);
+        clock.punch(&Color::White, &blitz, 2_000).unwrap();
+        assert_eq!(clock.white_ms, 300_000);
+        clock.punch(&Color::Black, &blitz, 10_000).unwrap();
+        assert_eq!(clock.black_ms, 295_000);
+    }
+
+    #[test]
+    fn correspondence_gives_the_days_per_move_again_after_every_move() {
+        let correspondence = TimeControl {
+            kind: ClockKind::Correspondence,
+            initial: 0,
+            increment: 0,
+            days_per_move: 2,
+        };
+        let mut clock = running(&correspondence, 0);
+        assert_eq!(clock.white_ms, 2 * MILLIS_PER_DAY);
+        clock
+            .punch(&Color::White, &correspondence, MILLIS_PER_DAY + 1)
+            .unwrap();
+        assert_eq!(clock.white_ms, 2 * MILLIS_PER_DAY);
+        let deadline = MILLIS_PER_DAY + 1 + 2 * MILLIS_PER_DAY;
+        assert!(!clock.is_flagged(&Color::Black, &correspondence, deadline - 1));
+        assert!(clock.is_flagged(&Color::Black, &correspondence, deadline));
+    }
+
+    #[test]
+    fn a_fallen_flag_refuses_the_move_and_stops_at_zero() {
+        let bullet = time_control(ClockKind::Fischer, 60, 1);
+        let mut clock = running(&bullet, 0);
+        assert!(!clock.is_flagged(&Color::White, &bullet, 60_999));
+        assert!(clock.is_flagged(&Color::White, &bullet, 61_000));
+        assert_eq!(
+            clock.punch(&Color::White, &bullet, 62_000),
+            Err(String::from("your time is up"))
+        );
+        clock.stop(&Color::White, &bullet, 62_000);
+        assert_eq!(clock.white_ms, 0);
+        assert_eq!(clock.turn_started_at, None);
+    }
+
+    #[test]
+    fn restart_charges_the_turn_and_resume_starts_it_again() {
+        let blitz = time_control(ClockKind::Fischer, 180, 2);
+        let mut clock = running(&blitz, 0);
+        clock.restart(&Color::White, &blitz, 30_000);
+        assert_eq!(clock.white_ms, 152_000);
+        assert_eq!(clock.turn_started_at, Some(30_000));
+
+        let resumed = clock.resume(90_000);
+        assert_eq!(resumed.white_ms, 152_000);
+        assert_eq!(resumed.turn_started_at, Some(90_000));
+        let waiting = Clock::new(&blitz).resume(90_000);
+        assert_eq!(waiting.turn_started_at, None);
+        //? a stopped clock stays stopped
+        let mut stopped = Clock::new(&blitz);
+        stopped.restart(&Color::White, &blitz, 30_000);
+        assert_eq!(stopped, Clock::new(&blitz));
+    }
+}
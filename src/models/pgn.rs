@@ -0,0 +1,382 @@
+use super::{Board, Color, DrawReason, GameStatus, Move, STARTING_FEN};
+
+//? PGN lines should not be longer than 80 characters
+const MAX_LINE_LENGTH: usize = 80;
+
+//? the Seven Tag Roster, the result tag is taken from the game status
+pub struct PgnHeader {
+    pub event: String,
+    pub site: String,
+    pub date: String,
+    pub round: String,
+    pub white: String,
+    pub black: String,
+}
+
+impl Default for PgnHeader {
+    fn default() -> Self {
+        PgnHeader {
+            event: String::from("Casual Game"),
+            site: String::from("chess_backend"),
+            date: String::from("????.??.??"),
+            round: String::from("-"),
+            white: String::from("?"),
+            black: String::from("?"),
+        }
+    }
+}
+
+//? the date tag of a game created at a timestamp of the DB, unknown parts stay question marks
+pub fn pgn_date(timestamp: &str) -> String {
+    match chrono::NaiveDateTime::parse_from_str(timestamp, "%Y-%m-%d %H:%M:%S") {
+        Ok(created_at) => created_at.format("%Y.%m.%d").to_string(),
+        Err(_) => PgnHeader::default().date,
+    }
+}
+
+pub fn write_pgn(
+    header: &PgnHeader,
+    start_fen: &str,
+    moves: &[String],
+    status: &GameStatus,
+) -> String {
+    let mut pgn = String::new();
+    for (tag, value) in [
+        ("Event", &header.event),
+        ("Site", &header.site),
+        ("Date", &header.date),
+        ("Round", &header.round),
+        ("White", &header.white),
+        ("Black", &header.black),
+    ] {
+        pgn.push_str(&format!("[{} \"{}\"]\n", tag, escape(value)));
+    }
+    pgn.push_str(&format!("[Result \"{}\"]\n", status.result()));
+    if start_fen != STARTING_FEN {
+        pgn.push_str("[SetUp \"1\"]\n");
+        pgn.push_str(&format!("[FEN \"{}\"]\n", start_fen));
+    }
+    pgn.push('\n');
+
+    //? the move numbers continue from the starting position
+    let fields = start_fen.split_whitespace().collect::<Vec<&str>>();
+    let mut move_number = fields
+        .get(5)
+        .and_then(|number| number.parse::<u32>().ok())
+        .unwrap_or(1);
+    let mut white_to_move = fields.get(1) != Some(&"b");
+
+    let mut tokens = Vec::new();
+    for (i, san) in moves.iter().enumerate() {
+        if white_to_move {
+            tokens.push(format!("{}.", move_number));
+        } else if i == 0 {
+            tokens.push(format!("{}...", move_number));
+        }
+        tokens.push(san.clone());
+        if !white_to_move {
+            move_number += 1;
+        }
+        white_to_move = !white_to_move;
+    }
+    tokens.push(String::from(status.result()));
+
+    let mut line = String::new();
+    for token in tokens {
+        if !line.is_empty() && line.len() + 1 + token.len() > MAX_LINE_LENGTH {
+            pgn.push_str(&line);
+            pgn.push('\n');
+            line.clear();
+        }
+        if !line.is_empty() {
+            line.push(' ');
+        }
+        line.push_str(&token);
+    }
+    pgn.push_str(&line);
+    pgn.push('\n');
+    pgn
+}
+
+fn escape(value: &str) -> String {
+    value.replace('\\', "\\\\").replace('"', "\\\"")
+}
+
+pub struct ParsedPgn {
+    pub tags: Vec<(String, String)>,
+    pub moves: Vec<String>,
+    pub result: Option<String>,
+}
+
+impl ParsedPgn {
+    pub fn tag(&self, name: &str) -> Option<&str> {
+        self.tags
+            .iter()
+            .find(|(tag, _)| tag == name)
+            .map(|(_, value)| value.as_str())
+    }
+
+    pub fn start_fen(&self) -> &str {
+        self.tag("FEN").unwrap_or(STARTING_FEN)
+    }
+}
+
+//? reads the first game of the input, comments, variations and NAGs are skipped
+pub fn parse_pgn(input: &str) -> Result<ParsedPgn, String> {
+    let mut tags = Vec::new();
+    let mut moves = Vec::new();
+    let mut result = None;
+    let mut chars = input.chars().peekable();
+    let mut token = String::new();
+
+    while let Some(c) = chars.next() {
+        match c {
+            '[' if moves.is_empty() => {
+                let tag = chars.by_ref().take_while(|c| *c != ']').collect::<String>();
+                tags.push(parse_tag(&tag)?);
+                continue;
+            }
+            '{' => {
+                let closed = chars.by_ref().any(|c| c == '}');
+                if !closed {
+                    return Err(String::from("unterminated comment"));
+                }
+            }
+            ';' => {
+                chars.by_ref().find(|c| *c == '\n');
+            }
+            '(' => {
+                let mut depth = 1;
+                for c in chars.by_ref() {
+                    match c {
+                        '(' => depth += 1,
+                        ')' => depth -= 1,
+                        _ => {}
+                    }
+                    if depth == 0 {
+                        break;
+                    }
+                }
+                if depth != 0 {
+                    return Err(String::from("unterminated variation"));
+                }
+            }
+            c if !c.is_whitespace() => {
+                token.push(c);
+                if chars.peek().is_some() {
+                    continue;
+                }
+            }
+            _ => {}
+        }
+
+        //? every other character ends the current token
+        if token.is_empty() {
+            continue;
+        }
+        match token.as_str() {
+            "1-0" | "0-1" | "1/2-1/2" | "*" => {
+                result = Some(token.clone());
+                break;
+            }
+            _ => {
+                if let Some(san) = strip_move_number(&token) {
+                    if !san.starts_with('$') {
+                        moves.push(String::from(san));
+                    }
+                }
+            }
+        }
+        token.clear();
+    }
+
+    Ok(ParsedPgn {
+        tags,
+        moves,
+        result,
+    })
+}
+
+fn parse_tag(tag: &str) -> Result<(String, String), String> {
+    let (name, value) = tag
+        .trim()
+        .split_once(char::is_whitespace)
+        .ok_or(format!("invalid tag [{}]", tag))?;
+    let value = value
+        .trim()
+        .strip_prefix('"')
+        .and_then(|value| value.strip_suffix('"'))
+        .ok_or(format!("invalid tag value in [{}]", tag))?;
+    Ok((
+        String::from(name),
+        value.replace("\\\"", "\"").replace("\\\\", "\\"),
+    ))
+}
+
+//? "12." and "12..." are dropped, "12.e4" becomes "e4". Only digits followed by a dot are a
+//? move number, castling written with zeros like 0-0 is kept.
+fn strip_move_number(token: &str) -> Option<&str> {
+    let san = token.trim_start_matches(|c: char| c.is_ascii_digit());
+    if san.len() == token.len() || !san.starts_with('.') {
+        return Some(token);
+    }
+    let san = san.trim_start_matches('.');
+    if san.is_empty() {
+        None
+    } else {
+        Some(san)
+    }
+}
+
+//? the status of a game whose result is known but not decided on the board, a decisive result
+//? is a resignation unless the Termination tag says the flag fell. None for unfinished games.
+pub fn result_status(parsed: &ParsedPgn) -> Option<GameStatus> {
+    let on_time = parsed.tag("Termination") == Some("time forfeit");
+    let winner = match parsed.result.as_deref().or(parsed.tag("Result"))? {
+        "1-0" => Color::White,
+        "0-1" => Color::Black,
+        "1/2-1/2" => {
+            return Some(GameStatus::Draw {
+                reason: DrawReason::Agreement,
+            })
+        }
+        _ => return None,
+    };
+    Some(if on_time {
+        GameStatus::Timeout { winner }
+    } else {
+        GameStatus::Resignation { winner }
+    })
+}
+
+//? plays the moves of the game from its starting position, the ply of the first illegal move is reported
+pub fn replay(parsed: &ParsedPgn) -> Result<(Board, Vec<Move>), String> {
+    replay_moves(parsed.start_fen(), &parsed.moves)
+}
+
+pub fn replay_moves(start_fen: &str, sans: &[String]) -> Result<(Board, Vec<Move>), String> {
+    let mut board = Board::from_fen(start_fen)?;
+    let mut moves = Vec::with_capacity(sans.len());
+    for (ply, san) in sans.iter().enumerate() {
+        let (from, to, promotion) = board
+            .parse_san(san)
+            .map_err(|e| format!("illegal move at ply {}: {}", ply + 1, e))?;
+        let moved = board
+            .move_piece(&from, &to, promotion)
+            .map_err(|e| format!("illegal move {} at ply {}: {}", san, ply + 1, e))?;
+        moves.push(moved);
+    }
+    Ok((board, moves))
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn comments_variations_and_nags_are_skipped() {
+        let parsed = parse_pgn(
+            "[Event \"Casual Game\"]
+[White \"Anna \\\"the Rook\\\" Schmidt\"]
+
+1. e4 {the best by test} e5 (1... c5 2. Nf3 (2. c3) d6) 2. Nf3 $1 ; the main line
+Nc6 3.Bb5 a6 *",
+        )
+        .unwrap();
+        assert_eq!(parsed.tag("Event"), Some("Casual Game"));
+        assert_eq!(parsed.tag("White"), Some("Anna \"the Rook\" Schmidt"));
+        assert_eq!(parsed.tag("Black"), None);
+        assert_eq!(parsed.moves, ["e4", "e5", "Nf3", "Nc6", "Bb5", "a6"]);
+        assert_eq!(parsed.result.as_deref(), Some("*"));
+        assert_eq!(parsed.start_fen(), STARTING_FEN);
+    }
+
+    #[test]
+    fn unterminated_comments_and_variations_are_errors() {
+        assert!(parse_pgn("1. e4 {a comment e5 *").is_err());
+        assert!(parse_pgn("1. e4 (1. d4 d5 *").is_err());
+        assert!(parse_pgn("[Event Casual]\n\n1. e4 *").is_err());
+    }
+
+    #[test]
+    fn written_games_read_back_the_same() {
+        let start_fen = "4k3/8/8/8/8/8/4P3/4K3 b - - 0 12";
+        let moves = ["Kd7", "e4", "Ke6", "Ke2"].map(String::from);
+        let header = PgnHeader {
+            white: String::from("Anna \"the Rook\""),
+            ..Default::default()
+        };
+        let status = GameStatus::Resignation {
+            winner: Color::White,
+        };
+        let pgn = write_pgn(&header, start_fen, &moves, &status);
+        assert!(pgn.contains("12... Kd7 13. e4 Ke6 14. Ke2 1-0"), "{pgn}");
+
+        let parsed = parse_pgn(&pgn).unwrap();
+        assert_eq!(parsed.tag("White"), Some("Anna \"the Rook\""));
+        assert_eq!(parsed.tag("SetUp"), Some("1"));
+        assert_eq!(parsed.start_fen(), start_fen);
+        assert_eq!(parsed.moves, moves);
+        assert_eq!(result_status(&parsed), Some(status));
+        let (board, _) = replay(&parsed).unwrap();
+        assert_eq!(board.to_fen(), "8/8/4k3/8/4P3/8/4K3/8 b - - 2 14");
+    }
+
+    const ZERO_CASTLING: &str = "[Event \"Club Championship\"]
+[Result \"1-0\"]
+
+1. e4 e5 2. Nf3 Nc6 3. Bc4 Bc5 4. 0-0 d6 5. d3 Bg4 6. Nc3 Qd7 7. Be3 0-0-0 1-0
+";
+
+    #[test]
+    fn castling_with_zeros_is_not_a_move_number() {
+        let parsed = parse_pgn(ZERO_CASTLING).unwrap();
+        assert_eq!(parsed.moves[6], "0-0");
+        assert_eq!(parsed.moves[13], "0-0-0");
+        let (board, moves) = replay(&parsed).unwrap();
+        assert_eq!(moves[6].san, "O-O");
+        assert_eq!(moves[13].san, "O-O-O");
+        assert_eq!(board.players_turn, Color::White);
+    }
+
+    #[test]
+    fn dates_are_written_with_dots() {
+        assert_eq!(pgn_date("2026-10-18 09:30:00"), "2026.10.18");
+        assert_eq!(pgn_date("yesterday"), "????.??.??");
+    }
+
+    #[test]
+    fn move_numbers_are_stripped() {
+        assert_eq!(strip_move_number("12."), None);
+        assert_eq!(strip_move_number("12..."), None);
+        assert_eq!(strip_move_number("12.e4"), Some("e4"));
+        assert_eq!(strip_move_number("0-0"), Some("0-0"));
+        assert_eq!(strip_move_number("0-0-0"), Some("0-0-0"));
+    }
+
+    #[test]
+    fn the_result_decides_a_game_the_board_does_not() {
+        let parsed = parse_pgn(ZERO_CASTLING).unwrap();
+        assert_eq!(
+            result_status(&parsed),
+            Some(GameStatus::Resignation {
+                winner: Color::White
+            })
+        );
+        let parsed = parse_pgn("[Termination \"time forfeit\"]\n\n1. e4 0-1").unwrap();
+        assert_eq!(
+            result_status(&parsed),
+            Some(GameStatus::Timeout {
+                winner: Color::Black
+            })
+        );
+        let parsed = parse_pgn("1. e4 e5 1/2-1/2").unwrap();
+        assert_eq!(
+            result_status(&parsed),
+            Some(GameStatus::Draw {
+                reason: DrawReason::Agreement
+            })
+        );
+        assert_eq!(result_status(&parse_pgn("1. e4 *").unwrap()), None);
+    }
+}
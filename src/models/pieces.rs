@@ -65,6 +65,8 @@ impl Piece for Pawn {
                             if self.color != color {
                                 positions.push(p);
                             }
+                        } else if db.is_en_passant_target(&p, &self.color) {
+                            positions.push(p);
                         }
                     }
                     if let Some(negative_file) = rev_files.next() {
@@ -73,6 +75,8 @@ impl Piece for Pawn {
                             if self.color != color {
                                 positions.push(p);
                             }
+                        } else if db.is_en_passant_target(&p, &self.color) {
+                            positions.push(p);
                         }
                     }
                 }
@@ -97,6 +101,8 @@ impl Piece for Pawn {
                             if self.color != color {
                                 positions.push(p);
                             }
+                        } else if db.is_en_passant_target(&p, &self.color) {
+                            positions.push(p);
                         }
                     }
                     if let Some(negative_file) = rev_files.next() {
@@ -105,6 +111,8 @@ impl Piece for Pawn {
                             if self.color != color {
                                 positions.push(p);
                             }
+                        } else if db.is_en_passant_target(&p, &self.color) {
+                            positions.push(p);
                         }
                     }
                 }
@@ -477,7 +485,7 @@ impl Piece for Queen {
 
             match files.next() {
                 Some(positive_file) => {
-                    let higher_rank = if i > pos.rank { 0 } else { pos.rank - i };
+                    let higher_rank = pos.rank.saturating_sub(i);
                     let lower_rank = pos.rank + i;
 
                     let p_hor = Position::new(positive_file, pos.rank);
@@ -528,7 +536,7 @@ impl Piece for Queen {
         let (mut diag_top, mut horizontal_left, mut diag_bot) = (true, true, true);
 
         for i in 1..=range {
-            let higher_rank = if i > pos.rank { 0 } else { pos.rank - i };
+            let higher_rank = pos.rank.saturating_sub(i);
             let lower_rank = pos.rank + i;
 
             match rev_files.next() {
@@ -681,6 +689,32 @@ impl Piece for King {
             positions.push(Position::new(pos.file, negative_rank as u8));
         }
 
+        //? castling is only possible while the king and the rook are on their starting squares
+        let home_rank = match self.color {
+            Color::White => RANK_BOUND_MIN,
+            Color::Black => RANK_BOUND_MAX,
+        };
+        if *pos == Position::new('e', home_rank) {
+            let has_rook = |file: char| {
+                matches!(
+                    db.get_piece(&Position::new(file, home_rank)),
+                    Some(GameObject::Rook(rook)) if rook.color == self.color
+                )
+            };
+            if db.castling.king_side(&self.color)
+                && has_rook('h')
+                && db.can_castle(&self.color, home_rank, &['f', 'g'], &['e', 'f', 'g'])
+            {
+                positions.push(Position::new('g', home_rank));
+            }
+            if db.castling.queen_side(&self.color)
+                && has_rook('a')
+                && db.can_castle(&self.color, home_rank, &['b', 'c', 'd'], &['e', 'd', 'c'])
+            {
+                positions.push(Position::new('c', home_rank));
+            }
+        }
+
         positions
             .into_iter()
             .filter(|p| !db.is_piece_in_position_of_same_color(p, &self.color))
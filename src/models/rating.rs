@@ -0,0 +1,236 @@
+use super::{ClockKind, Color, GameStatus, TimeControl};
+use serde::{Deserialize, Serialize};
+use std::str::FromStr;
+
+//? how fast the volatility may change, the Glicko-2 paper suggests 0.3 to 1.2
+const TAU: f64 = 0.5;
+const GLICKO2_SCALE: f64 = 173.7178;
+const CONVERGENCE: f64 = 0.000001;
+pub const DEFAULT_RATING: f64 = 1500.0;
+pub const DEFAULT_DEVIATION: f64 = 350.0;
+pub const DEFAULT_VOLATILITY: f64 = 0.06;
+
+//? ratings are kept apart per kind of game, a blitz rating says little about correspondence
+#[derive(Debug, Clone, Copy, PartialEq, Serialize, Deserialize)]
+#[serde(rename_all = "snake_case")]
+pub enum RatingPool {
+    Bullet,
+    Blitz,
+    Rapid,
+    Classical,
+    Correspondence,
+}
+
+impl RatingPool {
+    pub fn as_str(&self) -> &'static str {
+        match self {
+            RatingPool::Bullet => "BULLET",
+            RatingPool::Blitz => "BLITZ",
+            RatingPool::Rapid => "RAPID",
+            RatingPool::Classical => "CLASSICAL",
+            RatingPool::Correspondence => "CORRESPONDENCE",
+        }
+    }
+
+    //? sorted by the estimated length of a game, initial time plus 40 increments
+    pub fn of(time_control: &TimeControl) -> Self {
+        if time_control.kind == ClockKind::Correspondence {
+            return RatingPool::Correspondence;
+        }
+        match time_control.initial + 40 * time_control.increment {
+            0..=179 => RatingPool::Bullet,
+            180..=479 => RatingPool::Blitz,
+            480..=1499 => RatingPool::Rapid,
+            _ => RatingPool::Classical,
+        }
+    }
+}
+
+impl FromStr for RatingPool {
+    type Err = String;
+    fn from_str(s: &str) -> Result<Self, Self::Err> {
+        match s {
+            "BULLET" => Ok(RatingPool::Bullet),
+            "BLITZ" => Ok(RatingPool::Blitz),
+            "RAPID" => Ok(RatingPool::Rapid),
+            "CLASSICAL" => Ok(RatingPool::Classical),
+            "CORRESPONDENCE" => Ok(RatingPool::Correspondence),
+            var => Err(format!("{} is not a rating pool", var)),
+        }
+    }
+}
+
+#[derive(Debug, Clone, PartialEq, Serialize)]
+pub struct Rating {
+    pub rating: f64,
+    pub deviation: f64,
+    pub volatility: f64,
+}
+
+impl Default for Rating {
+    fn default() -> Self {
+        Rating {
+            rating: DEFAULT_RATING,
+            deviation: DEFAULT_DEVIATION,
+            volatility: DEFAULT_VOLATILITY,
+        }
+    }
+}
+
+//? the score of white, None when the game has not ended
+pub fn white_score(status: &GameStatus) -> Option<f64> {
+    match status {
+        GameStatus::Ongoing => None,
+        _ => Some(match status.winner() {
+            Some(Color::White) => 1.0,
+            Some(Color::Black) => 0.0,
+            None => 0.5,
+        }),
+    }
+}
+
+impl Rating {
+    //? a rating period of a single game, so every rated game moves the rating right away
+    pub fn update(&self, opponent: &Rating, score: f64) -> Rating {
+        self.update_period(&[(opponent.clone(), score)])
+    }
+
+    //? the rating after a period with the given games and scores.
+    //? Follows the steps of http://www.glicko.net/glicko/glicko2.pdf
+    pub fn update_period(&self, games: &[(Rating, f64)]) -> Rating {
+        let mu = (self.rating - DEFAULT_RATING) / GLICKO2_SCALE;
+        let phi = self.deviation / GLICKO2_SCALE;
+
+        let mut inverse_variance = 0.0;
+        let mut improvement = 0.0;
+        for (opponent, score) in games {
+            let opponent_mu = (opponent.rating - DEFAULT_RATING) / GLICKO2_SCALE;
+            let opponent_phi = opponent.deviation / GLICKO2_SCALE;
+            let g = 1.0 / (1.0 + 3.0 * opponent_phi.powi(2) / std::f64::consts::PI.powi(2)).sqrt();
+            let expected = 1.0 / (1.0 + (-g * (mu - opponent_mu)).exp());
+            inverse_variance += g.powi(2) * expected * (1.0 - expected);
+            improvement += g * (score - expected);
+        }
+        let variance = 1.0 / inverse_variance;
+        let delta = variance * improvement;
+
+        let volatility = self.new_volatility(phi, variance, delta);
+        let phi_star = (phi.powi(2) + volatility.powi(2)).sqrt();
+        let new_phi = 1.0 / (1.0 / phi_star.powi(2) + 1.0 / variance).sqrt();
+        let new_mu = mu + new_phi.powi(2) * improvement;
+
+        Rating {
+            rating: new_mu * GLICKO2_SCALE + DEFAULT_RATING,
+            deviation: (new_phi * GLICKO2_SCALE).min(DEFAULT_DEVIATION),
+            volatility,
+        }
+    }
+
+    //? step 5, the Illinois algorithm finds the root of f
+    fn new_volatility(&self, phi: f64, variance: f64, delta: f64) -> f64 {
+        let a = (self.volatility.powi(2)).ln();
+        let f = |x: f64| {
+            let ex = x.exp();
+            ex * (delta.powi(2) - phi.powi(2) - variance - ex)
+                / (2.0 * (phi.powi(2) + variance + ex).powi(2))
+                - (x - a) / TAU.powi(2)
+        };
+
+        let mut lower = a;
+        let mut upper = if delta.powi(2) > phi.powi(2) + variance {
+            (delta.powi(2) - phi.powi(2) - variance).ln()
+        } else {
+            let mut k = 1.0;
+            while f(a - k * TAU) < 0.0 {
+                k += 1.0;
+            }
+            a - k * TAU
+        };
+        let mut f_lower = f(lower);
+        let mut f_upper = f(upper);
+        while (upper - lower).abs() > CONVERGENCE {
+            let c = lower + (lower - upper) * f_lower / (f_upper - f_lower);
+            let f_c = f(c);
+            if f_c * f_upper <= 0.0 {
+                lower = upper;
+                f_lower = f_upper;
+            } else {
+                f_lower /= 2.0;
+            }
+            upper = c;
+            f_upper = f_c;
+        }
+        (lower / 2.0).exp()
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn rating(rating: f64, deviation: f64) -> Rating {
+        Rating {
+            rating,
+            deviation,
+            volatility: DEFAULT_VOLATILITY,
+        }
+    }
+
+    //? the example of section 3 of the Glicko-2 paper
+    #[test]
+    fn matches_the_example_of_glickman() {
+        let updated = rating(1500.0, 200.0).update_period(&[
+            (rating(1400.0, 30.0), 1.0),
+            (rating(1550.0, 100.0), 0.0),
+            (rating(1700.0, 300.0), 0.0),
+        ]);
+        assert!((updated.rating - 1464.06).abs() < 0.01, "{:?}", updated);
+        assert!((updated.deviation - 151.52).abs() < 0.01, "{:?}", updated);
+        assert!(
+            (updated.volatility - 0.05999).abs() < 0.00001,
+            "{:?}",
+            updated
+        );
+    }
+
+    #[test]
+    fn a_win_against_an_equal_opponent_gains_what_the_loser_loses() {
+        let winner = Rating::default().update(&Rating::default(), 1.0);
+        let loser = Rating::default().update(&Rating::default(), 0.0);
+        assert!(winner.rating > DEFAULT_RATING);
+        assert!((winner.rating - DEFAULT_RATING - (DEFAULT_RATING - loser.rating)).abs() < 1e-9);
+        assert!(winner.deviation < DEFAULT_DEVIATION);
+    }
+
+    #[test]
+    fn draws_score_half_a_point() {
+        let status = GameStatus::Draw {
+            reason: crate::models::DrawReason::Agreement,
+        };
+        assert_eq!(white_score(&status), Some(0.5));
+        assert_eq!(white_score(&GameStatus::Ongoing), None);
+        assert_eq!(
+            white_score(&GameStatus::Resignation {
+                winner: Color::Black
+            }),
+            Some(0.0)
+        );
+    }
+
+    #[test]
+    fn pools_follow_the_estimated_game_length() {
+        let time_control = |initial, increment| TimeControl {
+            kind: ClockKind::Fischer,
+            initial,
+            increment,
+            days_per_move: 0,
+        };
+        assert_eq!(RatingPool::of(&time_control(60, 0)), RatingPool::Bullet);
+        assert_eq!(RatingPool::of(&time_control(180, 2)), RatingPool::Blitz);
+        assert_eq!(RatingPool::of(&time_control(600, 0)), RatingPool::Rapid);
+        assert_eq!(
+            RatingPool::of(&time_control(1800, 30)),
+            RatingPool::Classical
+        );
+    }
+}
@@ -0,0 +1,85 @@
+use argon2::{
+    password_hash::{PasswordHash, PasswordHasher, PasswordVerifier, SaltString},
+    Argon2,
+};
+use serde::Serialize;
+use sqlx::{sqlite::SqliteRow, FromRow, Row};
+use uuid::Uuid;
+
+const MIN_PASSWORD_LENGTH: usize = 8;
+const MAX_PASSWORD_LENGTH: usize = 128;
+const MAX_USERNAME_LENGTH: usize = 20;
+
+#[derive(Debug, Clone, Serialize)]
+pub struct User {
+    pub id: Uuid,
+    pub username: String,
+    pub created_at: String,
+}
+
+impl FromRow<'_, SqliteRow> for User {
+    fn from_row(row: &SqliteRow) -> Result<Self, sqlx::Error> {
+        Ok(Self {
+            id: Uuid::parse_str(row.try_get("id")?)
+                .map_err(|e| sqlx::Error::Decode(Box::new(e)))?,
+            username: row.try_get("username")?,
+            created_at: row.try_get("created_at")?,
+        })
+    }
+}
+
+//? usernames show up in urls and PGN tags, so they are kept to letters, digits, - and _
+pub fn validate_username(username: &str) -> Result<(), String> {
+    if username.len() < 3 || username.len() > MAX_USERNAME_LENGTH {
+        return Err(format!(
+            "the username needs 3 to {} characters",
+            MAX_USERNAME_LENGTH
+        ));
+    }
+    if !username
+        .chars()
+        .all(|c| c.is_ascii_alphanumeric() || c == '-' || c == '_')
+    {
+        return Err(String::from(
+            "the username can only have letters, digits, - and _",
+        ));
+    }
+    Ok(())
+}
+
+pub fn validate_password(password: &str) -> Result<(), String> {
+    if password.chars().count() < MIN_PASSWORD_LENGTH {
+        return Err(format!(
+            "the password needs at least {} characters",
+            MIN_PASSWORD_LENGTH
+        ));
+    }
+    //? argon2 hashes anything, but a huge password makes every login slow
+    if password.len() > MAX_PASSWORD_LENGTH {
+        return Err(format!(
+            "the password can have at most {} bytes",
+            MAX_PASSWORD_LENGTH
+        ));
+    }
+    Ok(())
+}
+
+//? a PHC string with the parameters and salt, so the defaults can change without breaking old hashes
+pub fn hash_password(password: &str) -> Result<String, String> {
+    //? a v4 uuid is 16 random bytes from the OS, which is what a salt needs
+    let salt = SaltString::encode_b64(Uuid::new_v4().as_bytes()).map_err(|e| e.to_string())?;
+    Argon2::default()
+        .hash_password(password.as_bytes(), &salt)
+        .map(|hash| hash.to_string())
+        .map_err(|e| e.to_string())
+}
+
+pub fn verify_password(password: &str, hash: &str) -> bool {
+    PasswordHash::new(hash)
+        .map(|hash| {
+            Argon2::default()
+                .verify_password(password.as_bytes(), &hash)
+                .is_ok()
+        })
+        .unwrap_or(false)
+}
@@ -1,30 +1,74 @@
-use super::db::DB;
-use super::models::Position;
+use super::db::{Conflict, Game, DB, SESSION_DAYS};
+use super::models::{
+    self,
+    clock::{self, Clock},
+    pgn::{self, PgnHeader},
+    rating::RatingPool,
+    user::{self, User},
+    Board, Color, DrawReason, GameEvent, GameSettings, GameStatus, Piece, Position, Promotion,
+    TimeControl, Variant, STARTING_FEN,
+};
 use actix_web::{
     error::ResponseError,
-    web::{self, Data, ServiceConfig},
-    Responder,
+    http::{header, StatusCode},
+    web, Error, HttpRequest, HttpResponse, Responder,
 };
-use serde::Serialize;
+use actix_ws::Message;
+use cookie::{Cookie, SameSite};
+use serde::{Deserialize, Serialize};
 use serde_json::json;
-use std::{error::Error, fmt::Display};
+use std::fmt::Display;
+use std::time::Duration;
+use tokio::sync::{broadcast::error::RecvError, mpsc};
+use tokio_stream::wrappers::ReceiverStream;
+use uuid::Uuid;
 
 pub fn routes(config: &mut actix_web::web::ServiceConfig) {
     config.service(web::scope("/").route("", web::get().to(health_check)));
-
+    config
+        .route("/register", web::post().to(register))
+        .route("/login", web::post().to(login))
+        .route("/logout", web::post().to(logout))
+        .route("/me", web::get().to(get_me))
+        .route("/leaderboard", web::get().to(get_leaderboard));
     config.service(
-        web::scope("/board")
-            .route("", web::get().to(get_board))
-            .route("check", web::get().to(is_check))
-            .route("reset", web::get().to(reset_board)),
+        web::scope("/users/{id}")
+            .route("/ratings", web::get().to(get_ratings))
+            .route("/stats", web::get().to(get_stats)),
     );
+
     config.service(
-        web::scope("/move")
-            //? the order of these routes matter because the second could consume the first
-            .route("/show/{position}", web::get().to(show_move))
-            .route("/{from}/{to}", web::get().to(move_piece)),
+        web::scope("/games")
+            .route("", web::post().to(create_game))
+            //? the order of these routes matter because the scope could consume the import
+            .route("/import", web::post().to(import_pgn))
+            .service(
+                web::scope("/{id}")
+                    .route("", web::get().to(get_board))
+                    .route("/check", web::get().to(is_check))
+                    .route("/fen", web::get().to(get_fen))
+                    .route("/fen", web::post().to(load_fen))
+                    .route("/attacked/{color}", web::get().to(attacked_squares))
+                    .route("/reset", web::post().to(reset_board))
+                    .route("/pgn", web::get().to(get_pgn))
+                    .route("/moves", web::get().to(get_moves))
+                    .route("/takeback", web::post().to(request_takeback))
+                    .route("/takeback/accept", web::post().to(accept_takeback))
+                    .route("/takeback/decline", web::post().to(decline_takeback))
+                    .route("/draw/offer", web::post().to(offer_draw))
+                    .route("/draw/accept", web::post().to(accept_draw))
+                    .route("/draw/decline", web::post().to(decline_draw))
+                    .route("/resign", web::post().to(resign))
+                    .route("/events", web::get().to(get_events))
+                    .route("/seat", web::post().to(take_seat))
+                    .route("/ws", web::get().to(watch_game))
+                    .route("/stream", web::get().to(stream_game))
+                    //? the order of these routes matter because the second could consume the first
+                    .route("/move/show/{position}", web::get().to(show_move))
+                    .route("/move/{san}", web::get().to(move_san))
+                    .route("/move/{from}/{to}", web::get().to(move_piece)),
+            ),
     );
-    config.service(web::scope("/reset").route("", web::get().to(reset_board)));
 }
 
 //? health check route
@@ -32,27 +76,194 @@ async fn health_check() -> impl Responder {
     "success"
 }
 
-async fn get_board(data: web::Data<DB>) -> impl Responder {
-    let data = data.into_inner();
-    let pieces = data.print().await;
-    let player_turn = data.get_player_turn().await.unwrap();
-    web::Json(json!({"player_turn":player_turn, "board":pieces}))
+//? looks up the game of the route, unknown ids are answered with 404
+async fn find_game(data: &web::Data<DB>, id: &Uuid) -> Result<Game, StatusError> {
+    match data.get_game(id).await {
+        Ok(Some(game)) => Ok(game),
+        Ok(None) => Err(StatusError(
+            StatusCode::NOT_FOUND,
+            String::from("game not found"),
+        )),
+        Err(e) => Err(StatusError(
+            StatusCode::INTERNAL_SERVER_ERROR,
+            e.to_string(),
+        )),
+    }
 }
 
-async fn is_check(data: web::Data<DB>) -> impl Responder {
-    let board = data.into_inner().get_board().await;
-    let mut checkmate: bool = false;
-    if board.check_for_checkmate(board.players_turn.clone()) {
-        checkmate = true;
+#[derive(Debug, Default, Deserialize)]
+#[serde(rename_all = "snake_case")]
+enum ColorChoice {
+    White,
+    Black,
+    #[default]
+    Random,
+}
+
+#[derive(Debug, Deserialize)]
+struct NewGameBody {
+    fen: Option<String>,
+    #[serde(default)]
+    color: ColorChoice,
+    variant: Option<Variant>,
+    time_control: Option<TimeControl>,
+    #[serde(default)]
+    rated: bool,
+}
+
+async fn create_game(
+    req: HttpRequest,
+    data: web::Data<DB>,
+    body: web::Json<NewGameBody>,
+) -> Result<impl Responder, Error> {
+    let body = body.into_inner();
+    let board = match &body.fen {
+        Some(fen) => Board::from_fen(fen).map_err(CustomError)?,
+        None => models::create_game().lock().unwrap().clone(),
+    };
+    let is_standard = board.to_fen() == STARTING_FEN;
+    let variant = match body.variant {
+        Some(Variant::Standard) if !is_standard => {
+            return Err(CustomError(String::from(
+                "standard games have to start from the standard position",
+            ))
+            .into())
+        }
+        Some(variant) => variant,
+        None if is_standard => Variant::Standard,
+        None => Variant::FromPosition,
+    };
+    if body.rated && variant != Variant::Standard {
+        return Err(CustomError(String::from("only standard games can be rated")).into());
     }
-    let pos = board.is_check();
+    if let Some(time_control) = &body.time_control {
+        time_control.validate().map_err(CustomError)?;
+    }
+    let user = current_user(&data, &req).await?;
+    //? the pool of a rating comes from the time control, and the result has to reach an account
+    if body.rated && body.time_control.is_none() {
+        return Err(CustomError(String::from("rated games need a time control")).into());
+    }
+    if body.rated && user.is_none() {
+        return Err(StatusError(
+            StatusCode::UNAUTHORIZED,
+            String::from("log in to play rated games"),
+        )
+        .into());
+    }
+    let color = match body.color {
+        ColorChoice::White => Color::White,
+        ColorChoice::Black => Color::Black,
+        //? the first byte of a v4 uuid is random
+        ColorChoice::Random if Uuid::new_v4().as_bytes()[0] < 128 => Color::White,
+        ColorChoice::Random => Color::Black,
+    };
+
+    let settings = GameSettings {
+        variant,
+        time_control: body.time_control,
+        rated: body.rated,
+    };
+    let (game, tokens) = data
+        .create_game(&board, &settings, Some(&color))
+        .await
+        .map_err(|e| CustomError(e.to_string()))?;
+    if let Some(user) = &user {
+        game.claim_seat(&color, user).await.map_err(persist_error)?;
+    }
+    let token = match color {
+        Color::White => &tokens.white,
+        Color::Black => &tokens.black,
+    };
+    Ok(HttpResponse::Ok()
+        .insert_header((header::SET_COOKIE, seat_cookie(game.id(), token)))
+        .json(
+            json!({"id": game.id(), "color": color, "tokens": tokens, "settings": settings, "fen": board.to_fen()}),
+        ))
+}
+
+async fn get_board(data: web::Data<DB>, id: web::Path<Uuid>) -> Result<impl Responder, Error> {
+    let game = find_game(&data, &id).await?;
+    let (board, version) = game.get_versioned_board().await;
+    check_flag(&game, &board, version).await?;
+    Ok(web::Json(game_state(&game).await?))
+}
+
+//? everything a player needs to show the game, also pushed to the sockets of the game
+async fn game_state(game: &Game) -> Result<serde_json::Value, StatusError> {
+    //? read before the board, so a stale board never comes with a newer version
+    let version = game.get_version().await.map_err(persist_error)?;
+    let pieces = game.print().await;
+    let player_turn = game.get_player_turn().await.map_err(persist_error)?;
+    let status = game.get_status().await.map_err(persist_error)?;
+    let takeback_by = game.get_takeback_offer().await.map_err(persist_error)?;
+    let draw_offer_by = game.get_draw_offer().await.map_err(persist_error)?;
+    let clock = game.get_clock().await.map_err(persist_error)?;
+    let moves = game.get_moves().await.map_err(persist_error)?;
+    let players = game.get_players().await.map_err(persist_error)?;
+    let is_check = game.get_board().await.is_in_check(&player_turn).is_some();
+    Ok(json!({
+        "player_turn": player_turn,
+        "status": status,
+        "is_check": is_check,
+        "version": version,
+        "ply": moves.len(),
+        "last_move": moves.last(),
+        "takeback_by": takeback_by,
+        "draw_offer_by": draw_offer_by,
+        "clock": clock_view(&clock, &player_turn),
+        "players": players,
+        "board": pieces,
+    }))
+}
+
+async fn is_check(data: web::Data<DB>, id: web::Path<Uuid>) -> Result<impl Responder, Error> {
+    let board = find_game(&data, &id).await?.get_board().await;
+    //? only the side to move can be in check
+    let pos = board.is_in_check(&board.players_turn);
     let is_check = pos.is_some();
-    web::Json(json!({"is_check": is_check, "is_checkmate": checkmate, "pos": pos}))
+    let checkmate = board.check_for_checkmate(board.players_turn.clone());
+    Ok(web::Json(
+        json!({"is_check": is_check, "is_checkmate": checkmate, "pos": pos, "color": board.players_turn, "status": board.status}),
+    ))
+}
+
+async fn attacked_squares(
+    data: web::Data<DB>,
+    path: web::Path<(Uuid, Color)>,
+) -> Result<impl Responder, Error> {
+    let (id, color) = path.into_inner();
+    let board = find_game(&data, &id).await?.get_board().await;
+    let squares = board
+        .get_attacked_squares(&color)
+        .iter()
+        .map(|pos| pos.to_string())
+        .collect::<Vec<String>>();
+    Ok(web::Json(squares))
 }
 
 #[derive(Debug, Serialize)]
 struct CustomError(String);
 
+//? like CustomError but with a status code other than 400
+#[derive(Debug)]
+struct StatusError(actix_web::http::StatusCode, String);
+
+impl Display for StatusError {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        write!(f, "{}", self.1)
+    }
+}
+
+impl ResponseError for StatusError {
+    fn status_code(&self) -> actix_web::http::StatusCode {
+        self.0
+    }
+    fn error_response(&self) -> actix_web::HttpResponse<actix_web::body::BoxBody> {
+        actix_web::HttpResponse::build(self.status_code()).json(self.to_string())
+    }
+}
+
 impl Display for CustomError {
     fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
         write!(f, "{}", self.0)
@@ -68,30 +279,747 @@ impl ResponseError for CustomError {
     }
 }
 
+#[derive(Debug, Deserialize)]
+struct MoveQuery {
+    promotion: Option<Promotion>,
+    version: Option<i64>,
+    token: Option<Uuid>,
+}
+
+#[derive(Debug, Deserialize)]
+struct VersionQuery {
+    version: Option<i64>,
+    token: Option<Uuid>,
+}
+
+//? a client that sends the version it has seen only moves if nobody else moved in between
+fn check_version(expected: Option<i64>, version: i64) -> Result<(), StatusError> {
+    match expected {
+        Some(expected) if expected != version => Err(StatusError(
+            StatusCode::CONFLICT,
+            format!("the game is at version {version}, not {expected}"),
+        )),
+        _ => Ok(()),
+    }
+}
+
+fn persist_error(e: Box<dyn std::error::Error>) -> StatusError {
+    if e.is::<Conflict>() {
+        StatusError(StatusCode::CONFLICT, e.to_string())
+    } else {
+        StatusError(StatusCode::INTERNAL_SERVER_ERROR, e.to_string())
+    }
+}
+
 async fn move_piece(
+    req: HttpRequest,
+    data: web::Data<DB>,
+    moved: web::Path<(Uuid, Position, Position)>,
+    query: web::Query<MoveQuery>,
+) -> Result<impl Responder, Error> {
+    let (id, from, to) = moved.into_inner();
+    let query = query.into_inner();
+    let game = find_game(&data, &id).await?;
+    let seat = find_player(&data, &req, &game, query.token).await?;
+    let (board, version) = game.get_versioned_board().await;
+    check_version(query.version, version)?;
+
+    play(&game, &seat, board, version, from, to, query.promotion).await
+}
+
+//? plays a move written in standard algebraic notation, e.g. Nbd7, exd6, O-O-O or e8=Q+
+async fn move_san(
+    req: HttpRequest,
     data: web::Data<DB>,
-    moved: web::Path<(Position, Position)>,
-) -> Result<impl Responder, CustomError> {
-    let db = data.into_inner();
-    let mut board = db.get_board().await;
-    let (from, to) = moved.into_inner();
+    path: web::Path<(Uuid, String)>,
+    query: web::Query<VersionQuery>,
+) -> Result<impl Responder, Error> {
+    let (id, san) = path.into_inner();
+    let game = find_game(&data, &id).await?;
+    let seat = find_player(&data, &req, &game, query.token).await?;
+    let (board, version) = game.get_versioned_board().await;
+    check_version(query.version, version)?;
+    let (from, to, promotion) = board.parse_san(&san).map_err(CustomError)?;
+
+    play(&game, &seat, board, version, from, to, promotion).await
+}
+
+//? validates the move on the board, charges the clock of the mover and stores both
+async fn play(
+    game: &Game,
+    seat: &Color,
+    mut board: Board,
+    version: i64,
+    from: Position,
+    to: Position,
+    promotion: Option<Promotion>,
+) -> Result<web::Json<serde_json::Value>, Error> {
+    if check_flag(game, &board, version).await?.is_some() {
+        return Err(CustomError(String::from("your time is up")).into());
+    }
+    //? the result of a rated game has to reach both accounts, so it waits for the opponent
+    if game.is_rated().await.map_err(persist_error)? {
+        let players = game.get_players().await.map_err(persist_error)?;
+        if players.white.is_none() || players.black.is_none() {
+            return Err(CustomError(String::from(
+                "a rated game starts once both players took their seat with an account",
+            ))
+            .into());
+        }
+    }
+    //? the board only knows whose turn it is, not who is asking
+    if let Some(piece) = board.get_piece(&from) {
+        if piece.get_color() != *seat {
+            return Err(StatusError(
+                StatusCode::FORBIDDEN,
+                format!("you play {:?} and can only move your own pieces", seat),
+            )
+            .into());
+        }
+    }
+    let mover = board.players_turn.clone();
+    let moved = board
+        .move_piece(&from, &to, promotion)
+        .map_err(CustomError)?;
+
+    let mut clock = game.get_clock().await.map_err(persist_error)?;
+    if let Some((time_control, clock)) = &mut clock {
+        clock
+            .punch(&mover, time_control, clock::now())
+            .map_err(CustomError)?;
+        if board.status != GameStatus::Ongoing {
+            clock.turn_started_at = None;
+        }
+    }
+    let version = game
+        .move_piece(
+            &moved,
+            &board,
+            clock.as_ref().map(|(_, clock)| clock),
+            version,
+        )
+        .await
+        .map_err(persist_error)?;
+
+    Ok(web::Json(
+        json!({"san": moved.san, "from": from, "to": to, "status": board.status, "version": version, "clock": clock_view(&clock, &board.players_turn)}),
+    ))
+}
 
-    board.move_piece(&from, &to).map_err(|e| CustomError(e))?;
+//? nobody is notified when a flag falls, so it is checked whenever the game is looked at or
+//? moved in and the game ends on time then. The opponent only wins with mating material.
+async fn check_flag(
+    game: &Game,
+    board: &Board,
+    version: i64,
+) -> Result<Option<GameStatus>, StatusError> {
+    let (time_control, mut clock) = match game.get_clock().await.map_err(persist_error)? {
+        Some(clock) => clock,
+        None => return Ok(None),
+    };
+    let now = clock::now();
+    if board.status != GameStatus::Ongoing
+        || !clock.is_flagged(&board.players_turn, &time_control, now)
+    {
+        return Ok(None);
+    }
+    clock.stop(&board.players_turn, &time_control, now);
+    let winner = board.players_turn.opposite_color();
+    let status = if board.has_insufficient_material_for(&winner) {
+        GameStatus::Draw {
+            reason: DrawReason::TimeoutVsInsufficientMaterial,
+        }
+    } else {
+        GameStatus::Timeout { winner }
+    };
+    match game.end_game(&status, None, Some(&clock), version).await {
+        Ok(_) => Ok(Some(status)),
+        //? requests that look at the game when the flag falls race to end it,
+        //? the ones that lose take the status the game ended with
+        Err(e) if e.is::<Conflict>() => {
+            let status = game.get_status().await.map_err(persist_error)?;
+            Ok((status != GameStatus::Ongoing).then_some(status))
+        }
+        Err(e) => Err(persist_error(e)),
+    }
+}
+
+//? the clock of a game that ends without a move stops with the time of the side to move
+async fn stopped_clock(game: &Game, side_to_move: &Color) -> Result<Option<Clock>, StatusError> {
+    let clock = game.get_clock().await.map_err(persist_error)?;
+    Ok(clock.map(|(time_control, mut clock)| {
+        clock.stop(side_to_move, &time_control, clock::now());
+        clock
+    }))
+}
+
+//? the remaining times at this moment in milliseconds
+fn clock_view(clock: &Option<(TimeControl, Clock)>, side_to_move: &Color) -> serde_json::Value {
+    let (time_control, clock) = match clock {
+        Some(clock) => clock,
+        None => return serde_json::Value::Null,
+    };
+    let now = clock::now();
+    let remaining = |color: &Color| {
+        clock
+            .remaining_at(color, side_to_move, time_control, now)
+            .max(0)
+    };
+    json!({
+        "time_control": time_control,
+        "white": remaining(&Color::White),
+        "black": remaining(&Color::Black),
+        "running": clock.turn_started_at.map(|_| side_to_move),
+    })
+}
+
+#[derive(Debug, Deserialize)]
+struct SeatBody {
+    token: Uuid,
+}
+
+const SEAT_COOKIE: &str = "seat";
+
+//? the token sent with the request, or else the one in the seat cookie of the game
+fn seat_token(req: &HttpRequest, token: Option<Uuid>) -> Option<Uuid> {
+    token.or_else(|| cookie_token(req, SEAT_COOKIE))
+}
+
+fn cookie_token(req: &HttpRequest, name: &str) -> Option<Uuid> {
+    req.headers()
+        .get_all(header::COOKIE)
+        .filter_map(|cookies| cookies.to_str().ok())
+        .flat_map(Cookie::split_parse)
+        .filter_map(Result::ok)
+        .find(|cookie| cookie.name() == name)
+        .and_then(|cookie| Uuid::parse_str(cookie.value()).ok())
+}
+
+//? the cookie is scoped to the game, so a browser can sit at several games at once
+fn seat_cookie(game_id: &Uuid, token: &Uuid) -> String {
+    Cookie::build(SEAT_COOKIE, token.to_string())
+        .path(format!("/games/{game_id}"))
+        .http_only(true)
+        .same_site(SameSite::Lax)
+        .finish()
+        .to_string()
+}
+
+//? the opponent opens the invitation with the token of the other seat and gets its cookie
+async fn take_seat(
+    req: HttpRequest,
+    data: web::Data<DB>,
+    id: web::Path<Uuid>,
+    body: web::Json<SeatBody>,
+) -> Result<impl Responder, Error> {
+    let game = find_game(&data, &id).await?;
+    let color = find_seat(&game, Some(body.token)).await?;
+    let user = current_user(&data, &req).await?;
+    //? the result of a rated game has to reach two different accounts
+    if game.is_rated().await.map_err(persist_error)? {
+        let user = user.as_ref().ok_or(StatusError(
+            StatusCode::UNAUTHORIZED,
+            String::from("log in to play rated games"),
+        ))?;
+        let players = game.get_players().await.map_err(persist_error)?;
+        let opponent = match color {
+            Color::White => players.black,
+            Color::Black => players.white,
+        };
+        if opponent.map(|opponent| opponent.id) == Some(user.id) {
+            return Err(StatusError(
+                StatusCode::FORBIDDEN,
+                String::from("you can not play a rated game against yourself"),
+            )
+            .into());
+        }
+    }
+    //? a logged in player takes the seat with their account
+    if let Some(user) = user {
+        if !game
+            .claim_seat(&color, &user)
+            .await
+            .map_err(persist_error)?
+        {
+            return Err(StatusError(
+                StatusCode::FORBIDDEN,
+                String::from("another account already sits at this seat"),
+            )
+            .into());
+        }
+    }
+    Ok(HttpResponse::Ok()
+        .insert_header((header::SET_COOKIE, seat_cookie(&id, &body.token)))
+        .json(json!({"color": color})))
+}
+
+//? the color of the player the token belongs to, nobody else can act for a side
+async fn find_seat(game: &Game, token: Option<Uuid>) -> Result<Color, StatusError> {
+    let token = token.ok_or(StatusError(
+        StatusCode::FORBIDDEN,
+        String::from("only the players of this game can do this, send the token of your seat"),
+    ))?;
+    match game.get_seat(&token).await {
+        Ok(Some(color)) => Ok(color),
+        Ok(None) => Err(StatusError(
+            StatusCode::FORBIDDEN,
+            String::from("the token does not belong to a player of this game"),
+        )),
+        Err(e) => Err(StatusError(
+            StatusCode::INTERNAL_SERVER_ERROR,
+            e.to_string(),
+        )),
+    }
+}
+
+//? the seat of the requesting player. The token alone is not enough for a seat that is linked
+//? to an account or for any seat of a rated game, there it has to come from that account.
+async fn find_player(
+    data: &web::Data<DB>,
+    req: &HttpRequest,
+    game: &Game,
+    token: Option<Uuid>,
+) -> Result<Color, StatusError> {
+    let seat = find_seat(game, seat_token(req, token)).await?;
+    let user = current_user(data, req).await?;
+    check_account(game, &seat, user.as_ref()).await?;
+    Ok(seat)
+}
+
+async fn check_account(game: &Game, seat: &Color, user: Option<&User>) -> Result<(), StatusError> {
+    let players = game.get_players().await.map_err(persist_error)?;
+    let account = match seat {
+        Color::White => players.white,
+        Color::Black => players.black,
+    };
+    match account {
+        Some(account) if user.map(|user| user.id) == Some(account.id) => Ok(()),
+        Some(account) => Err(StatusError(
+            StatusCode::FORBIDDEN,
+            format!(
+                "this seat belongs to {}, log in with that account",
+                account.username
+            ),
+        )),
+        None if game.is_rated().await.map_err(persist_error)? => Err(StatusError(
+            StatusCode::FORBIDDEN,
+            String::from("take the seat with your account to play a rated game"),
+        )),
+        None => Ok(()),
+    }
+}
+
+//? the last move of the requesting side is taken back, together with the reply to it if there is one
+fn takeback_plies(board: &Board, requested_by: &Color) -> usize {
+    if board.players_turn == *requested_by {
+        2
+    } else {
+        1
+    }
+}
+
+async fn request_takeback(
+    data: web::Data<DB>,
+    id: web::Path<Uuid>,
+    req: HttpRequest,
+    body: Option<web::Json<SeatBody>>,
+) -> Result<impl Responder, Error> {
+    let game = find_game(&data, &id).await?;
+    let color = find_player(&data, &req, &game, body.map(|body| body.token)).await?;
+    let (board, version) = game.get_versioned_board().await;
+    if board.status != GameStatus::Ongoing {
+        return Err(CustomError(String::from("the game is already over")).into());
+    }
+    let moves = game.get_san_moves().await.map_err(persist_error)?;
+    if moves.len() < takeback_plies(&board, &color) {
+        return Err(CustomError(String::from("there is no move of yours to take back")).into());
+    }
+    let version = game
+        .set_takeback_offer(Some(&color), version)
+        .await
+        .map_err(persist_error)?;
+    Ok(web::Json(json!({"takeback_by": color, "version": version})))
+}
+
+async fn accept_takeback(
+    data: web::Data<DB>,
+    id: web::Path<Uuid>,
+    req: HttpRequest,
+    body: Option<web::Json<SeatBody>>,
+) -> Result<impl Responder, Error> {
+    let game = find_game(&data, &id).await?;
+    let color = find_player(&data, &req, &game, body.map(|body| body.token)).await?;
+    //? read before the offer, so a withdrawal or the end of the game in between fails on the version
+    let (board, version) = game.get_versioned_board().await;
+    if board.status != GameStatus::Ongoing {
+        return Err(CustomError(String::from("the game is already over")).into());
+    }
+    let requested_by = match game.get_takeback_offer().await.map_err(persist_error)? {
+        Some(requested_by) if requested_by != color => requested_by,
+        Some(_) => {
+            return Err(CustomError(String::from("the opponent has to accept the takeback")).into())
+        }
+        None => return Err(CustomError(String::from("no takeback was requested")).into()),
+    };
+    let plies = takeback_plies(&board, &requested_by);
+    let moves = game.get_san_moves().await.map_err(persist_error)?;
+    if moves.len() < plies {
+        return Err(CustomError(String::from("there is no move to take back")).into());
+    }
+    let remaining_plies = moves.len() - plies;
+    let start_fen = game.get_start_fen().await.map_err(persist_error)?;
+    let (previous, _) =
+        pgn::replay_moves(&start_fen, &moves[..remaining_plies]).map_err(CustomError)?;
+
+    //? the times go back with the position, so the increments of the undone moves are gone
+    let restored = game
+        .get_clock_after(remaining_plies as i64)
+        .await
+        .map_err(persist_error)?;
+    let mut clock = game.get_clock().await.map_err(persist_error)?;
+    if let Some((time_control, clock)) = &mut clock {
+        let now = clock::now();
+        match restored {
+            Some(restored) => *clock = restored.resume(now),
+            None if remaining_plies == 0 => *clock = Clock::new(time_control),
+            //? moves stored before their clocks were kept can only be charged up to now
+            None => clock.restart(&board.players_turn, time_control, now),
+        }
+    }
+    let version = game
+        .take_back(
+            &previous,
+            plies as i64,
+            clock.as_ref().map(|(_, clock)| clock),
+            version,
+        )
+        .await
+        .map_err(persist_error)?;
+
+    Ok(web::Json(
+        json!({"plies": plies, "fen": previous.to_fen(), "player_turn": previous.players_turn, "version": version}),
+    ))
+}
+
+//? the opponent declines or the requesting player withdraws the takeback
+async fn decline_takeback(
+    data: web::Data<DB>,
+    id: web::Path<Uuid>,
+    req: HttpRequest,
+    body: Option<web::Json<SeatBody>>,
+) -> Result<impl Responder, Error> {
+    let game = find_game(&data, &id).await?;
+    find_player(&data, &req, &game, body.map(|body| body.token)).await?;
+    let version = game.get_version().await.map_err(persist_error)?;
+    if game
+        .get_takeback_offer()
+        .await
+        .map_err(persist_error)?
+        .is_none()
+    {
+        return Err(CustomError(String::from("no takeback was requested")).into());
+    }
+    let version = game
+        .set_takeback_offer(None, version)
+        .await
+        .map_err(persist_error)?;
+    Ok(web::Json(json!({"version": version})))
+}
+
+//? offering a draw while the opponent offers one accepts it
+async fn offer_draw(
+    data: web::Data<DB>,
+    id: web::Path<Uuid>,
+    req: HttpRequest,
+    body: Option<web::Json<SeatBody>>,
+) -> Result<impl Responder, Error> {
+    let game = find_game(&data, &id).await?;
+    let color = find_player(&data, &req, &game, body.map(|body| body.token)).await?;
+    let (board, version) = game.get_versioned_board().await;
+    if board.status != GameStatus::Ongoing {
+        return Err(CustomError(String::from("the game is already over")).into());
+    }
+    match game.get_draw_offer().await.map_err(persist_error)? {
+        Some(offered_by) if offered_by == color => {
+            Err(CustomError(String::from("you already offered a draw")).into())
+        }
+        Some(_) => agree_to_draw(&game, &board, &color, version).await,
+        None => {
+            let version = game
+                .set_draw_offer(Some(&color), &GameEvent::DrawOffer, &color, version)
+                .await
+                .map_err(persist_error)?;
+            Ok(web::Json(
+                json!({"draw_offer_by": color, "version": version}),
+            ))
+        }
+    }
+}
+
+async fn accept_draw(
+    data: web::Data<DB>,
+    id: web::Path<Uuid>,
+    req: HttpRequest,
+    body: Option<web::Json<SeatBody>>,
+) -> Result<impl Responder, Error> {
+    let game = find_game(&data, &id).await?;
+    let color = find_player(&data, &req, &game, body.map(|body| body.token)).await?;
+    let (board, version) = game.get_versioned_board().await;
+    if board.status != GameStatus::Ongoing {
+        return Err(CustomError(String::from("the game is already over")).into());
+    }
+    match game.get_draw_offer().await.map_err(persist_error)? {
+        Some(offered_by) if offered_by != color => {
+            agree_to_draw(&game, &board, &color, version).await
+        }
+        Some(_) => Err(CustomError(String::from("the opponent has to accept the draw")).into()),
+        None => Err(CustomError(String::from("no draw was offered")).into()),
+    }
+}
 
-    db.move_piece(from, to)
+async fn agree_to_draw(
+    game: &Game,
+    board: &Board,
+    color: &Color,
+    version: i64,
+) -> Result<web::Json<serde_json::Value>, Error> {
+    let status = GameStatus::Draw {
+        reason: DrawReason::Agreement,
+    };
+    let clock = stopped_clock(game, &board.players_turn).await?;
+    let version = game
+        .end_game(
+            &status,
+            Some((&GameEvent::DrawAccept, color)),
+            clock.as_ref(),
+            version,
+        )
+        .await
+        .map_err(persist_error)?;
+    Ok(web::Json(json!({"status": status, "version": version})))
+}
+
+//? the opponent declines or the offering player withdraws the draw
+async fn decline_draw(
+    data: web::Data<DB>,
+    id: web::Path<Uuid>,
+    req: HttpRequest,
+    body: Option<web::Json<SeatBody>>,
+) -> Result<impl Responder, Error> {
+    let game = find_game(&data, &id).await?;
+    let color = find_player(&data, &req, &game, body.map(|body| body.token)).await?;
+    let version = game.get_version().await.map_err(persist_error)?;
+    if game
+        .get_draw_offer()
+        .await
+        .map_err(persist_error)?
+        .is_none()
+    {
+        return Err(CustomError(String::from("no draw was offered")).into());
+    }
+    let version = game
+        .set_draw_offer(None, &GameEvent::DrawDecline, &color, version)
+        .await
+        .map_err(persist_error)?;
+    Ok(web::Json(json!({"version": version})))
+}
+
+async fn resign(
+    data: web::Data<DB>,
+    id: web::Path<Uuid>,
+    req: HttpRequest,
+    body: Option<web::Json<SeatBody>>,
+) -> Result<impl Responder, Error> {
+    let game = find_game(&data, &id).await?;
+    let color = find_player(&data, &req, &game, body.map(|body| body.token)).await?;
+    let (board, version) = game.get_versioned_board().await;
+    if board.status != GameStatus::Ongoing {
+        return Err(CustomError(String::from("the game is already over")).into());
+    }
+    let status = GameStatus::Resignation {
+        winner: color.opposite_color(),
+    };
+    let clock = stopped_clock(&game, &board.players_turn).await?;
+    let version = game
+        .end_game(
+            &status,
+            Some((&GameEvent::Resign, &color)),
+            clock.as_ref(),
+            version,
+        )
+        .await
+        .map_err(persist_error)?;
+    Ok(web::Json(json!({"status": status, "version": version})))
+}
+
+async fn get_events(data: web::Data<DB>, id: web::Path<Uuid>) -> Result<impl Responder, Error> {
+    let events = find_game(&data, &id)
+        .await?
+        .get_events()
+        .await
+        .map_err(persist_error)?;
+    Ok(web::Json(events))
+}
+
+async fn get_fen(data: web::Data<DB>, id: web::Path<Uuid>) -> Result<impl Responder, Error> {
+    let board = find_game(&data, &id).await?.get_board().await;
+    Ok(web::Json(json!({"fen": board.to_fen()})))
+}
+
+#[derive(Debug, Deserialize)]
+struct FenBody {
+    fen: String,
+    token: Option<Uuid>,
+}
+
+//? only the creator sets up the position and only before the first move. Rated games keep
+//? their position, a loaded one could end the game without the ratings noticing.
+async fn check_setup(game: &Game, seat: &Color) -> Result<(), StatusError> {
+    if game.is_rated().await.map_err(persist_error)? {
+        return Err(StatusError(
+            StatusCode::FORBIDDEN,
+            String::from("the position of a rated game can not be changed"),
+        ));
+    }
+    if game.get_creator().await.map_err(persist_error)?.as_ref() != Some(seat) {
+        return Err(StatusError(
+            StatusCode::FORBIDDEN,
+            String::from("only the player who created the game can change its position"),
+        ));
+    }
+    if !game
+        .get_san_moves()
+        .await
+        .map_err(persist_error)?
+        .is_empty()
+    {
+        return Err(StatusError(
+            StatusCode::BAD_REQUEST,
+            String::from("the position can only be changed before the first move"),
+        ));
+    }
+    Ok(())
+}
+
+async fn load_fen(
+    req: HttpRequest,
+    data: web::Data<DB>,
+    id: web::Path<Uuid>,
+    body: web::Json<FenBody>,
+) -> Result<impl Responder, Error> {
+    let game = find_game(&data, &id).await?;
+    let seat = find_player(&data, &req, &game, body.token).await?;
+    //? read before the checks, so a move in between fails on the version
+    let version = game.get_version().await.map_err(persist_error)?;
+    check_setup(&game, &seat).await?;
+    let board = Board::from_fen(&body.fen).map_err(CustomError)?;
+    let version = game
+        .load_board(&board, version)
+        .await
+        .map_err(persist_error)?;
+    Ok(web::Json(
+        json!({"fen": board.to_fen(), "version": version}),
+    ))
+}
+
+async fn get_moves(data: web::Data<DB>, id: web::Path<Uuid>) -> Result<impl Responder, Error> {
+    let moves = find_game(&data, &id)
+        .await?
+        .get_moves()
+        .await
+        .map_err(|e| StatusError(StatusCode::INTERNAL_SERVER_ERROR, e.to_string()))?;
+    Ok(web::Json(moves))
+}
+
+async fn get_pgn(
+    req: HttpRequest,
+    data: web::Data<DB>,
+    id: web::Path<Uuid>,
+) -> Result<impl Responder, StatusError> {
+    let game = find_game(&data, &id).await?;
+    let internal_error = |e: Box<dyn std::error::Error>| {
+        StatusError(StatusCode::INTERNAL_SERVER_ERROR, e.to_string())
+    };
+    let moves = game.get_san_moves().await.map_err(internal_error)?;
+    let start_fen = game.get_start_fen().await.map_err(internal_error)?;
+    let status = game.get_status().await.map_err(internal_error)?;
+    let header = pgn_header(&req, &game).await.map_err(internal_error)?;
+    let pgn = pgn::write_pgn(&header, &start_fen, &moves, &status);
+
+    Ok(HttpResponse::Ok()
+        .content_type("application/x-chess-pgn")
+        .body(pgn))
+}
+
+//? names the game by its kind, links back to it and fills in the accounts at its seats
+async fn pgn_header(
+    req: &HttpRequest,
+    game: &Game,
+) -> Result<PgnHeader, Box<dyn std::error::Error>> {
+    let site = {
+        let connection = req.connection_info();
+        format!(
+            "{}://{}/games/{}",
+            connection.scheme(),
+            connection.host(),
+            game.id()
+        )
+    };
+    let kind = if game.is_rated().await? {
+        "Rated"
+    } else {
+        "Casual"
+    };
+    let event = match game.get_clock().await? {
+        Some((time_control, _)) => format!(
+            "{} {} game",
+            kind,
+            RatingPool::of(&time_control).as_str().to_lowercase()
+        ),
+        None => format!("{} game", kind),
+    };
+    let players = game.get_players().await?;
+    let name = |user: Option<User>| user.map(|user| user.username);
+    let default = PgnHeader::default();
+    Ok(PgnHeader {
+        event,
+        site,
+        date: pgn::pgn_date(&game.get_created_at().await?),
+        white: name(players.white).unwrap_or(default.white),
+        black: name(players.black).unwrap_or(default.black),
+        ..default
+    })
+}
+
+async fn import_pgn(data: web::Data<DB>, body: String) -> Result<impl Responder, CustomError> {
+    let parsed = pgn::parse_pgn(&body).map_err(CustomError)?;
+    let (mut board, moves) = pgn::replay(&parsed).map_err(CustomError)?;
+    //? a game that ended by resignation, agreement or on time keeps its result
+    if board.status == GameStatus::Ongoing {
+        if let Some(status) = pgn::result_status(&parsed) {
+            board.status = status;
+        }
+    }
+    let settings = GameSettings {
+        variant: Variant::of(parsed.start_fen()),
+        ..GameSettings::default()
+    };
+    let (game, tokens) = data
+        .import_game(parsed.start_fen(), &moves, &board, &settings)
         .await
         .map_err(|e| CustomError(e.to_string()))?;
 
-    Ok(web::Json("success"))
+    Ok(web::Json(
+        json!({"id": game.id(), "tokens": tokens, "plies": moves.len(), "result": parsed.result, "status": board.status}),
+    ))
 }
 
 async fn show_move(
     data: web::Data<DB>,
-    pos: web::Path<Position>,
-) -> Result<impl Responder, CustomError> {
-    let db = data.into_inner();
-    let board = db.get_board().await;
-    let pos = pos.into_inner();
+    path: web::Path<(Uuid, Position)>,
+) -> Result<impl Responder, Error> {
+    let (id, pos) = path.into_inner();
+    let board = find_game(&data, &id).await?.get_board().await;
     let moves = board.show_moves_of_tile(&pos);
     let moves = moves
         .iter()
@@ -101,7 +1029,442 @@ async fn show_move(
     Ok(web::Json(moves))
 }
 
-async fn reset_board(data: web::Data<DB>) -> Result<impl Responder, Box<dyn Error>> {
-    data.into_inner().reset().await?;
-    Ok(web::Json("success"))
+async fn reset_board(
+    data: web::Data<DB>,
+    id: web::Path<Uuid>,
+    req: HttpRequest,
+    body: Option<web::Json<SeatBody>>,
+) -> Result<impl Responder, Error> {
+    let game = find_game(&data, &id).await?;
+    let seat = find_player(&data, &req, &game, body.map(|body| body.token)).await?;
+    let version = game.get_version().await.map_err(persist_error)?;
+    check_setup(&game, &seat).await?;
+    let version = game.reset(version).await.map_err(persist_error)?;
+    Ok(web::Json(json!({"version": version})))
+}
+
+//? a move sent over the socket of a game, either {"san": "Nf3"} or {"from": "g1", "to": "f3"}.
+//? The token can be left out when the seat cookie came with the socket.
+#[derive(Debug, Deserialize)]
+struct SocketMove {
+    token: Option<Uuid>,
+    san: Option<String>,
+    from: Option<Position>,
+    to: Option<Position>,
+    promotion: Option<Promotion>,
+    version: Option<i64>,
+}
+
+//? players and spectators get the state of the game on every change and the clock every second,
+//? moves can be played over the same socket
+async fn watch_game(
+    req: HttpRequest,
+    body: web::Payload,
+    data: web::Data<DB>,
+    id: web::Path<Uuid>,
+) -> Result<HttpResponse, Error> {
+    let game = find_game(&data, &id).await?;
+    let (response, mut session, mut messages) = actix_ws::handle(&req, body)?;
+    let mut updates = game.subscribe();
+    let cookie_token = seat_token(&req, None);
+    //? the account of the socket is the one it was opened with
+    let user = current_user(&data, &req).await?;
+
+    actix_web::rt::spawn(async move {
+        let mut ticks = tokio::time::interval(Duration::from_secs(1));
+        if push_state(&mut session, &game).await.is_err() {
+            return;
+        }
+        loop {
+            let sent = tokio::select! {
+                update = updates.recv() => match update {
+                    //? a lagging socket skips to the latest state
+                    Ok(_) | Err(RecvError::Lagged(_)) => push_state(&mut session, &game).await,
+                    Err(RecvError::Closed) => break,
+                },
+                message = messages.recv() => match message {
+                    Some(Ok(Message::Text(text))) => match play_socket_move(&game, cookie_token, user.as_ref(), &text).await {
+                        //? the mover sees the move with everybody else through the update
+                        Ok(()) => Ok(()),
+                        Err(e) => push(&mut session, "error", json!(e)).await,
+                    },
+                    Some(Ok(Message::Ping(bytes))) => session.pong(&bytes).await,
+                    Some(Ok(Message::Close(reason))) => {
+                        let _ = session.close(reason).await;
+                        return;
+                    }
+                    Some(Ok(_)) => Ok(()),
+                    Some(Err(_)) | None => break,
+                },
+                _ = ticks.tick() => push_clock(&mut session, &game).await,
+            };
+            if sent.is_err() {
+                break;
+            }
+        }
+        let _ = session.close(None).await;
+    });
+
+    Ok(response)
+}
+
+async fn play_socket_move(
+    game: &Game,
+    cookie_token: Option<Uuid>,
+    user: Option<&User>,
+    text: &str,
+) -> Result<(), String> {
+    let SocketMove {
+        token,
+        san,
+        from,
+        to,
+        promotion,
+        version: expected,
+    } = serde_json::from_str(text).map_err(|e| e.to_string())?;
+    let seat = find_seat(game, token.or(cookie_token))
+        .await
+        .map_err(|e| e.to_string())?;
+    check_account(game, &seat, user)
+        .await
+        .map_err(|e| e.to_string())?;
+    let (board, version) = game.get_versioned_board().await;
+    check_version(expected, version).map_err(|e| e.to_string())?;
+    let (from, to, promotion) = match (san, from, to) {
+        (Some(san), _, _) => board.parse_san(&san)?,
+        (None, Some(from), Some(to)) => (from, to, promotion),
+        _ => return Err(String::from("a move needs a san or from and to")),
+    };
+    play(game, &seat, board, version, from, to, promotion)
+        .await
+        .map_err(|e| e.to_string())?;
+    Ok(())
+}
+
+async fn push(
+    session: &mut actix_ws::Session,
+    kind: &str,
+    data: serde_json::Value,
+) -> Result<(), actix_ws::Closed> {
+    session
+        .text(json!({"type": kind, "data": data}).to_string())
+        .await
+}
+
+async fn push_state(session: &mut actix_ws::Session, game: &Game) -> Result<(), actix_ws::Closed> {
+    match game_state(game).await {
+        Ok(state) => push(session, "state", state).await,
+        Err(e) => push(session, "error", json!(e.to_string())).await,
+    }
+}
+
+//? sends the running clock, a fallen flag ends the game which reaches the socket as an update
+async fn push_clock(session: &mut actix_ws::Session, game: &Game) -> Result<(), actix_ws::Closed> {
+    let (board, version) = game.get_versioned_board().await;
+    if let Err(e) = check_flag(game, &board, version).await {
+        return push(session, "error", json!(e.to_string())).await;
+    }
+    match game.get_clock().await {
+        Ok(Some(clock)) if clock.1.turn_started_at.is_some() => {
+            push(
+                session,
+                "clock",
+                clock_view(&Some(clock), &board.players_turn),
+            )
+            .await
+        }
+        Ok(_) => Ok(()),
+        Err(e) => push(session, "error", json!(e.to_string())).await,
+    }
+}
+
+//? the same updates as the socket for networks that block WebSockets. Every event has the ply
+//? it was sent at as its id, so a reconnecting client gets the moves after its Last-Event-ID.
+async fn stream_game(
+    req: HttpRequest,
+    data: web::Data<DB>,
+    id: web::Path<Uuid>,
+) -> Result<HttpResponse, Error> {
+    let game = find_game(&data, &id).await?;
+    let mut sent_ply = req
+        .headers()
+        .get("Last-Event-ID")
+        .and_then(|id| id.to_str().ok())
+        .and_then(|id| id.trim().parse::<usize>().ok());
+    let mut updates = game.subscribe();
+    let (sender, receiver) = mpsc::channel::<Result<web::Bytes, Error>>(16);
+
+    actix_web::rt::spawn(async move {
+        let mut keep_alive = tokio::time::interval(Duration::from_secs(15));
+        loop {
+            let events = match stream_events(&game, &mut sent_ply).await {
+                Ok(events) => events,
+                Err(e) => vec![stream_event(None, "error", &json!(e.to_string()))],
+            };
+            for event in events {
+                if sender.send(Ok(event)).await.is_err() {
+                    return;
+                }
+            }
+            //? comments keep proxies from closing an idle stream and notice a gone client
+            loop {
+                tokio::select! {
+                    update = updates.recv() => match update {
+                        Err(RecvError::Closed) => return,
+                        _ => break,
+                    },
+                    _ = keep_alive.tick() => {
+                        let comment = web::Bytes::from_static(b": keep-alive\n\n");
+                        if sender.send(Ok(comment)).await.is_err() {
+                            return;
+                        }
+                    }
+                }
+            }
+        }
+    });
+
+    Ok(HttpResponse::Ok()
+        .content_type("text/event-stream")
+        .insert_header(("Cache-Control", "no-cache"))
+        .streaming(ReceiverStream::new(receiver)))
+}
+
+//? the moves played since the last sent ply, followed by the state after them.
+//? A client without a Last-Event-ID only gets the state.
+async fn stream_events(
+    game: &Game,
+    sent_ply: &mut Option<usize>,
+) -> Result<Vec<web::Bytes>, StatusError> {
+    let state = game_state(game).await?;
+    let moves = game.get_moves().await.map_err(persist_error)?;
+    let mut events = Vec::new();
+    if let Some(sent_ply) = sent_ply {
+        for moved in moves.iter().skip(*sent_ply) {
+            events.push(stream_event(
+                Some(moved.ply as usize),
+                "move",
+                &json!(moved),
+            ));
+        }
+    }
+    events.push(stream_event(Some(moves.len()), "state", &state));
+    *sent_ply = Some(moves.len());
+    Ok(events)
+}
+
+fn stream_event(ply: Option<usize>, kind: &str, data: &serde_json::Value) -> web::Bytes {
+    let id = ply.map(|ply| format!("id: {ply}\n")).unwrap_or_default();
+    web::Bytes::from(format!("{id}event: {kind}\ndata: {data}\n\n"))
+}
+
+const SESSION_COOKIE: &str = "session";
+
+#[derive(Debug, Deserialize)]
+struct Credentials {
+    username: String,
+    password: String,
+}
+
+//? the account of the session cookie, None for guests and expired sessions
+async fn current_user(
+    data: &web::Data<DB>,
+    req: &HttpRequest,
+) -> Result<Option<User>, StatusError> {
+    match cookie_token(req, SESSION_COOKIE) {
+        Some(token) => data.get_session_user(&token).await.map_err(persist_error),
+        None => Ok(None),
+    }
+}
+
+fn session_cookie(token: &Uuid) -> String {
+    Cookie::build(SESSION_COOKIE, token.to_string())
+        .path("/")
+        .http_only(true)
+        .same_site(SameSite::Lax)
+        .max_age(cookie::time::Duration::days(SESSION_DAYS))
+        .finish()
+        .to_string()
+}
+
+async fn start_session(data: &web::Data<DB>, user: &User) -> Result<HttpResponse, StatusError> {
+    let token = data.create_session(user).await.map_err(persist_error)?;
+    Ok(HttpResponse::Ok()
+        .insert_header((header::SET_COOKIE, session_cookie(&token)))
+        .json(user))
+}
+
+async fn register(
+    data: web::Data<DB>,
+    body: web::Json<Credentials>,
+) -> Result<impl Responder, Error> {
+    let Credentials { username, password } = body.into_inner();
+    user::validate_username(&username).map_err(CustomError)?;
+    user::validate_password(&password).map_err(CustomError)?;
+    //? hashing takes a while on purpose, so it does not run on the server threads
+    let password_hash = web::block(move || user::hash_password(&password))
+        .await?
+        .map_err(|e| StatusError(StatusCode::INTERNAL_SERVER_ERROR, e))?;
+    let user = data
+        .create_user(&username, &password_hash)
+        .await
+        .map_err(persist_error)?
+        .ok_or(StatusError(
+            StatusCode::CONFLICT,
+            String::from("the username is taken"),
+        ))?;
+    Ok(start_session(&data, &user).await?)
+}
+
+async fn login(data: web::Data<DB>, body: web::Json<Credentials>) -> Result<impl Responder, Error> {
+    let Credentials { username, password } = body.into_inner();
+    let wrong_login = || {
+        StatusError(
+            StatusCode::UNAUTHORIZED,
+            String::from("wrong username or password"),
+        )
+    };
+    let (user, password_hash) = data
+        .get_login(&username)
+        .await
+        .map_err(persist_error)?
+        .ok_or_else(wrong_login)?;
+    let verified = web::block(move || user::verify_password(&password, &password_hash)).await?;
+    if !verified {
+        return Err(wrong_login().into());
+    }
+    Ok(start_session(&data, &user).await?)
+}
+
+async fn logout(req: HttpRequest, data: web::Data<DB>) -> Result<impl Responder, Error> {
+    if let Some(token) = cookie_token(&req, SESSION_COOKIE) {
+        data.delete_session(&token).await.map_err(persist_error)?;
+    }
+    let mut expired = Cookie::build(SESSION_COOKIE, "").path("/").finish();
+    expired.make_removal();
+    Ok(HttpResponse::Ok()
+        .insert_header((header::SET_COOKIE, expired.to_string()))
+        .json("success"))
+}
+
+async fn get_me(req: HttpRequest, data: web::Data<DB>) -> Result<impl Responder, Error> {
+    let user = current_user(&data, &req).await?.ok_or(StatusError(
+        StatusCode::UNAUTHORIZED,
+        String::from("not logged in"),
+    ))?;
+    let games = data.get_user_games(&user).await.map_err(persist_error)?;
+    let ratings = data.get_ratings(&user).await.map_err(persist_error)?;
+    Ok(web::Json(
+        json!({"user": user, "ratings": ratings, "games": games}),
+    ))
+}
+
+#[derive(Debug, Deserialize)]
+struct RatingQuery {
+    pool: Option<RatingPool>,
+}
+
+async fn find_user(data: &web::Data<DB>, id: &Uuid) -> Result<User, StatusError> {
+    data.get_user(id)
+        .await
+        .map_err(persist_error)?
+        .ok_or(StatusError(
+            StatusCode::NOT_FOUND,
+            String::from("user not found"),
+        ))
+}
+
+//? the current rating in every pool and how it got there
+async fn get_ratings(
+    data: web::Data<DB>,
+    id: web::Path<Uuid>,
+    query: web::Query<RatingQuery>,
+) -> Result<impl Responder, Error> {
+    let user = find_user(&data, &id).await?;
+    let ratings = data.get_ratings(&user).await.map_err(persist_error)?;
+    let history = data
+        .get_rating_history(&user, query.pool.as_ref())
+        .await
+        .map_err(persist_error)?;
+    Ok(web::Json(
+        json!({"user": user, "ratings": ratings, "history": history}),
+    ))
+}
+
+const MAX_PAGE_SIZE: u32 = 100;
+
+#[derive(Debug, Deserialize)]
+struct LeaderboardQuery {
+    pool: RatingPool,
+    #[serde(default = "first_page")]
+    page: u32,
+    #[serde(default = "default_page_size")]
+    per_page: u32,
+}
+
+fn first_page() -> u32 {
+    1
+}
+
+fn default_page_size() -> u32 {
+    20
+}
+
+async fn get_leaderboard(
+    data: web::Data<DB>,
+    query: web::Query<LeaderboardQuery>,
+) -> Result<impl Responder, Error> {
+    if query.page == 0 || query.per_page == 0 || query.per_page > MAX_PAGE_SIZE {
+        return Err(CustomError(format!(
+            "pages start at 1 and have 1 to {} players",
+            MAX_PAGE_SIZE
+        ))
+        .into());
+    }
+    let (total, players) = data
+        .get_leaderboard(&query.pool, query.page, query.per_page)
+        .await
+        .map_err(persist_error)?;
+    Ok(web::Json(json!({
+        "pool": query.pool,
+        "page": query.page,
+        "per_page": query.per_page,
+        "total": total,
+        "players": players,
+    })))
+}
+
+#[derive(Debug, Deserialize)]
+struct StatsQuery {
+    #[serde(default = "default_recent_games")]
+    recent: u32,
+}
+
+fn default_recent_games() -> u32 {
+    10
+}
+
+async fn get_stats(
+    data: web::Data<DB>,
+    id: web::Path<Uuid>,
+    query: web::Query<StatsQuery>,
+) -> Result<impl Responder, Error> {
+    let user = find_user(&data, &id).await?;
+    let stats = data
+        .get_user_stats(&user, query.recent.min(MAX_PAGE_SIZE))
+        .await
+        .map_err(persist_error)?;
+    //? the results of the recent games as one line, newest first, like WDLWW
+    let form = stats
+        .recent
+        .iter()
+        .map(|game| match game.result.as_str() {
+            "win" => 'W',
+            "loss" => 'L',
+            _ => 'D',
+        })
+        .collect::<String>();
+    Ok(web::Json(
+        json!({"user": user, "stats": stats, "form": form}),
+    ))
 }